@@ -0,0 +1,242 @@
+//! `#[derive(BorshCompat)]` for Borsh structs that grow by appending fields.
+//!
+//! Every field is annotated with the struct version it was introduced in:
+//!
+//! ```ignore
+//! #[derive(BorshCompat)]
+//! pub struct Movie {
+//!     pub title: String,
+//!     pub genre: Genre,
+//!     #[borsh_compat(since = 2, default = "unknown_imdb_url")]
+//!     pub imdb_url: String,
+//! }
+//! ```
+//!
+//! Fields without the attribute belong to version 1. Versions may only grow in
+//! declaration order, so the layout of version `n` is always a prefix of the newest
+//! layout. The derive generates:
+//!
+//! * `BorshSerialize`, which always writes the newest layout;
+//! * `BorshDeserialize`, which reads any historical layout. A version's fields are
+//!   read if input remains when they are reached, and otherwise take their `default`
+//!   (a path to a `fn() -> T`, or `Default::default`). Like `Appended`, this is only
+//!   sound for a struct that is the last thing in its buffer;
+//! * `LATEST_VERSION` and `deserialize_version`, for reading a layout whose version
+//!   is known from elsewhere, e.g. an envelope tag. These work anywhere in a buffer.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Lit, Meta, NestedMeta, Path};
+
+struct CompatField {
+    ident: syn::Ident,
+    since: u8,
+    default: Option<Path>,
+}
+
+#[proc_macro_derive(BorshCompat, attributes(borsh_compat))]
+pub fn derive_borsh_compat(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "BorshCompat only supports structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "BorshCompat only supports structs",
+            ))
+        }
+    };
+
+    let mut compat_fields: Vec<CompatField> = Vec::new();
+    for field in fields {
+        let compat_field = parse_field(field)?;
+        if let Some(previous) = compat_fields.last() {
+            if compat_field.since < previous.since {
+                return Err(Error::new_spanned(
+                    field,
+                    format!(
+                        "field introduced in version {} follows `{}` from version {}; \
+                         new fields may only be appended",
+                        compat_field.since, previous.ident, previous.since
+                    ),
+                ));
+            }
+        }
+        compat_fields.push(compat_field);
+    }
+    let latest_version = compat_fields.last().map_or(1, |field| field.since);
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let idents: Vec<_> = compat_fields.iter().map(|field| &field.ident).collect();
+
+    let tail_reads = compat_fields.iter().scan(1, |present_version, field| {
+        let ident = &field.ident;
+        let present = format_ident!("__present_v{}", field.since);
+        let mut tokens = TokenStream2::new();
+        if field.since == 1 {
+            tokens.extend(quote! {
+                let #ident = ::borsh::BorshDeserialize::deserialize(buf)?;
+            });
+            return Some(tokens);
+        }
+        let default = default_expr(field);
+        if field.since > *present_version {
+            let previous = if *present_version == 1 {
+                quote!(true)
+            } else {
+                let previous = format_ident!("__present_v{}", *present_version);
+                quote!(#previous)
+            };
+            tokens.extend(quote! {
+                let #present = #previous && !buf.is_empty();
+            });
+            *present_version = field.since;
+        }
+        tokens.extend(quote! {
+            let #ident = if #present {
+                ::borsh::BorshDeserialize::deserialize(buf)?
+            } else {
+                #default
+            };
+        });
+        Some(tokens)
+    });
+
+    let version_reads = compat_fields.iter().map(|field| {
+        let ident = &field.ident;
+        let since = field.since;
+        if since == 1 {
+            return quote! {
+                let #ident = ::borsh::BorshDeserialize::deserialize(buf)?;
+            };
+        }
+        let default = default_expr(field);
+        quote! {
+            let #ident = if version >= #since {
+                ::borsh::BorshDeserialize::deserialize(buf)?
+            } else {
+                #default
+            };
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::borsh::BorshSerialize for #name #ty_generics #where_clause {
+            fn serialize<W: ::borsh::maybestd::io::Write>(
+                &self,
+                writer: &mut W,
+            ) -> ::core::result::Result<(), ::borsh::maybestd::io::Error> {
+                #(::borsh::BorshSerialize::serialize(&self.#idents, writer)?;)*
+                Ok(())
+            }
+        }
+
+        impl #impl_generics ::borsh::BorshDeserialize for #name #ty_generics #where_clause {
+            fn deserialize(
+                buf: &mut &[u8],
+            ) -> ::core::result::Result<Self, ::borsh::maybestd::io::Error> {
+                #(#tail_reads)*
+                Ok(Self { #(#idents),* })
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// The version whose layout `BorshSerialize` writes.
+            pub const LATEST_VERSION: u8 = #latest_version;
+
+            /// Reads exactly the layout of `version`, filling newer fields with their defaults.
+            pub fn deserialize_version(
+                buf: &mut &[u8],
+                version: u8,
+            ) -> ::core::result::Result<Self, ::borsh::maybestd::io::Error> {
+                if version == 0 || version > Self::LATEST_VERSION {
+                    return Err(::borsh::maybestd::io::Error::new(
+                        ::borsh::maybestd::io::ErrorKind::InvalidData,
+                        ::std::format!("Unknown {} version {}", stringify!(#name), version),
+                    ));
+                }
+                #(#version_reads)*
+                Ok(Self { #(#idents),* })
+            }
+        }
+    })
+}
+
+fn default_expr(field: &CompatField) -> TokenStream2 {
+    match &field.default {
+        Some(path) => quote!(#path()),
+        None => quote!(::core::default::Default::default()),
+    }
+}
+
+fn parse_field(field: &syn::Field) -> syn::Result<CompatField> {
+    let ident = field.ident.clone().expect("named fields have identifiers");
+    let mut since = 1;
+    let mut default = None;
+    for attr in &field.attrs {
+        if !attr.path.is_ident("borsh_compat") {
+            continue;
+        }
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => {
+                return Err(Error::new_spanned(
+                    meta,
+                    "expected #[borsh_compat(since = N, default = \"path\")]",
+                ))
+            }
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(pair)) if pair.path.is_ident("since") => {
+                    since = match &pair.lit {
+                        Lit::Int(lit) => lit.base10_parse::<u8>()?,
+                        lit => return Err(Error::new_spanned(lit, "`since` must be an integer")),
+                    };
+                    if since == 0 {
+                        return Err(Error::new_spanned(pair, "versions start at 1"));
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(pair)) if pair.path.is_ident("default") => {
+                    default = match &pair.lit {
+                        Lit::Str(lit) => Some(lit.parse::<Path>()?),
+                        lit => return Err(Error::new_spanned(lit, "`default` must be a string")),
+                    };
+                }
+                nested => {
+                    return Err(Error::new_spanned(
+                        nested,
+                        "unknown borsh_compat option, expected `since` or `default`",
+                    ))
+                }
+            }
+        }
+    }
+    if since == 1 && default.is_some() {
+        return Err(Error::new_spanned(
+            field,
+            "`default` only applies to fields added after version 1",
+        ));
+    }
+    Ok(CompatField {
+        ident,
+        since,
+        default,
+    })
+}
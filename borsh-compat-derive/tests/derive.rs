@@ -0,0 +1,101 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use borsh_compat_derive::BorshCompat;
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+enum Genre {
+    Comedy,
+    Drama,
+    Fantasy,
+    Horror,
+    Romance,
+    ScienceFiction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BorshCompat)]
+struct Movie {
+    title: String,
+    genre: Genre,
+    #[borsh_compat(since = 2, default = "unknown_imdb_url")]
+    imdb_url: String,
+    #[borsh_compat(since = 3)]
+    runtime_minutes: u16,
+    #[borsh_compat(since = 3)]
+    year: u16,
+}
+
+fn unknown_imdb_url() -> String {
+    "https://www.imdb.com/".into()
+}
+
+const V1_HEX: &str = "120000004261636b20546f205468652046757475726505";
+
+fn back_to_the_future() -> Movie {
+    Movie {
+        title: "Back To The Future".into(),
+        genre: Genre::ScienceFiction,
+        imdb_url: "https://www.imdb.com/title/tt0088763/".into(),
+        runtime_minutes: 116,
+        year: 1985,
+    }
+}
+
+#[test]
+fn test_decodes_v1_layout() {
+    let input = hex::decode(V1_HEX).unwrap();
+    let movie = Movie::try_from_slice(&input).unwrap();
+    assert_eq!(
+        movie,
+        Movie {
+            title: "Back To The Future".into(),
+            genre: Genre::ScienceFiction,
+            imdb_url: unknown_imdb_url(),
+            runtime_minutes: 0,
+            year: 0,
+        }
+    );
+}
+
+#[test]
+fn test_decodes_v2_layout() {
+    let mut input = hex::decode(V1_HEX).unwrap();
+    back_to_the_future().imdb_url.serialize(&mut input).unwrap();
+    let movie = Movie::try_from_slice(&input).unwrap();
+    assert_eq!(movie.imdb_url, back_to_the_future().imdb_url);
+    assert_eq!((movie.runtime_minutes, movie.year), (0, 0));
+}
+
+#[test]
+fn test_latest_round_trip() {
+    assert_eq!(Movie::LATEST_VERSION, 3);
+    let movie = back_to_the_future();
+    let serialized_movie = movie.try_to_vec().unwrap();
+    assert_eq!(Movie::try_from_slice(&serialized_movie).unwrap(), movie);
+}
+
+#[test]
+fn test_rejects_partial_version() {
+    // Version 3 adds two fields at once, so only one of them is a truncated record.
+    let mut input = hex::decode(V1_HEX).unwrap();
+    back_to_the_future().imdb_url.serialize(&mut input).unwrap();
+    116u16.serialize(&mut input).unwrap();
+    assert!(Movie::try_from_slice(&input).is_err());
+}
+
+#[test]
+fn test_deserialize_version_inside_a_buffer() {
+    let mut input = hex::decode(V1_HEX).unwrap();
+    input.extend_from_slice(&input.clone());
+    let mut buf = input.as_slice();
+    let first = Movie::deserialize_version(&mut buf, 1).unwrap();
+    let second = Movie::deserialize_version(&mut buf, 1).unwrap();
+    assert!(buf.is_empty());
+    assert_eq!(first, second);
+    assert_eq!(first.imdb_url, unknown_imdb_url());
+}
+
+#[test]
+fn test_deserialize_version_rejects_unknown_versions() {
+    let input = back_to_the_future().try_to_vec().unwrap();
+    assert!(Movie::deserialize_version(&mut input.as_slice(), 0).is_err());
+    assert!(Movie::deserialize_version(&mut input.as_slice(), 4).is_err());
+}
@@ -0,0 +1,45 @@
+//! Feeds arbitrary bytes to every `Movie` decoder. Run with `cargo +nightly fuzz run decode_movie`.
+
+#![no_main]
+
+use borsh_backwards_compatibility_example::fuzz::{allocation_limit, check_decoders};
+use libfuzzer_sys::fuzz_target;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Records the largest single allocation, so a hostile length prefix that makes a
+/// decoder reserve memory it has no input for fails the run.
+struct LargestAllocation;
+
+static LARGEST: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for LargestAllocation {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LARGEST.fetch_max(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        LARGEST.fetch_max(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: LargestAllocation = LargestAllocation;
+
+fuzz_target!(|data: &[u8]| {
+    LARGEST.store(0, Ordering::Relaxed);
+    check_decoders(data);
+    let largest = LARGEST.load(Ordering::Relaxed);
+    assert!(
+        largest <= allocation_limit(data),
+        "allocated {} bytes for {} bytes of input",
+        largest,
+        data.len()
+    );
+});
@@ -0,0 +1,236 @@
+//! Sampled search for byte strings that are a valid encoding under two `Movie` layouts.
+//!
+//! A `Movie` is usually decoded from the middle of a larger buffer, so a layout
+//! "accepts" a byte string when it can decode a prefix of it. Two layouts that both
+//! accept the same bytes would let a record change meaning depending on which decoder
+//! runs, which is exactly what adding a version must never do.
+//!
+//! The search space is every encoding the layouts themselves produce for a set of
+//! sample movies, plus every truncation of those encodings, every single-byte
+//! extension, and every value of each byte in the region where layouts are told apart.
+//! Finding nothing there is evidence, not proof. The proof that a tagged record is
+//! never a V1 record is structural, see [`crate::ENVELOPE_MARKER`], and is checked
+//! directly by the tests below.
+
+use crate::decode::{decode_tagged, Mode, Reader};
+use crate::{Genre, Movie, MovieV1, MovieV2, MovieVersion, ENVELOPE_MARKER};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Bytes at the start of an encoding that are exhaustively mutated. This covers the
+/// version marker, the tag and the first byte of the payload.
+const MUTATED_PREFIX_LEN: usize = ENVELOPE_MARKER.len() + 2;
+
+/// One way of laying out a `Movie` on the wire.
+pub(crate) trait Layout {
+    fn label(&self) -> String;
+
+    /// Encodes the parts of `sample` that this layout can represent.
+    fn encode(&self, sample: &MovieV2) -> Vec<u8>;
+
+    /// Decodes a `Movie` from the start of `bytes`, returning how many bytes it used.
+    fn decode_prefix(&self, bytes: &[u8]) -> Option<usize>;
+}
+
+impl<L: Layout + ?Sized> Layout for Box<L> {
+    fn label(&self) -> String {
+        (**self).label()
+    }
+
+    fn encode(&self, sample: &MovieV2) -> Vec<u8> {
+        (**self).encode(sample)
+    }
+
+    fn decode_prefix(&self, bytes: &[u8]) -> Option<usize> {
+        (**self).decode_prefix(bytes)
+    }
+}
+
+impl Layout for MovieVersion {
+    fn label(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn encode(&self, sample: &MovieV2) -> Vec<u8> {
+        let movie = match self {
+            Self::V1 => Movie::V1(MovieV1 {
+                title: sample.title.clone(),
+                genre: sample.genre.clone(),
+            }),
+            Self::V2 => Movie::V2(sample.clone()),
+        };
+        movie.try_to_vec().expect("writing to a Vec cannot fail")
+    }
+
+    // Deliberately does not go through `Movie::deserialize`, which already picks a
+    // single layout; each version is decoded as a node that only knows it would.
+    fn decode_prefix(&self, bytes: &[u8]) -> Option<usize> {
+        if self.is_tagged() {
+            let header = [&ENVELOPE_MARKER[..], &[self.as_u8()]].concat();
+            if !bytes.starts_with(&header) {
+                return None;
+            }
+            let mut reader = Reader::at(bytes, header.len());
+            decode_tagged(&mut reader, *self, self.as_u8(), Mode::Lenient).ok()?;
+            Some(reader.offset())
+        } else {
+            let mut buf = bytes;
+            MovieV1::deserialize(&mut buf).ok()?;
+            Some(bytes.len() - buf.len())
+        }
+    }
+}
+
+/// A byte string accepted by more than one layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ambiguity {
+    pub bytes: Vec<u8>,
+    /// Label of each accepting layout, with the number of bytes it consumed.
+    pub accepted_by: Vec<(String, usize)>,
+}
+
+/// Movies covering empty, ASCII and multi-byte titles, every genre, and empty and
+/// non-empty URLs.
+pub fn sample_movies() -> Vec<MovieV2> {
+    let titles = ["", "a", "Back To The Future", "Amélie", "\u{1F3AC}"];
+    let imdb_urls = ["", "https://www.imdb.com/title/tt0088763/"];
+    let mut samples = Vec::new();
+    for title in titles {
+        for genre in Genre::ALL {
+            for imdb_url in imdb_urls {
+                samples.push(MovieV2 {
+                    title: title.into(),
+                    genre: genre.clone(),
+                    imdb_url: imdb_url.into(),
+                });
+            }
+        }
+    }
+    samples
+}
+
+/// The byte strings searched for ambiguities, derived from `samples` encoded with
+/// every one of `layouts`.
+pub(crate) fn candidates<L: Layout>(layouts: &[L], samples: &[MovieV2]) -> Vec<Vec<u8>> {
+    let mut candidates = Vec::new();
+    for layout in layouts {
+        for sample in samples {
+            let encoded = layout.encode(sample);
+            for end in 0..=encoded.len() {
+                candidates.push(encoded[..end].to_vec());
+            }
+            for byte in 0..=u8::MAX {
+                let mut extended = encoded.clone();
+                extended.push(byte);
+                candidates.push(extended);
+                for i in 0..MUTATED_PREFIX_LEN.min(encoded.len()) {
+                    let mut mutated = encoded.clone();
+                    mutated[i] = byte;
+                    candidates.push(mutated);
+                }
+            }
+        }
+    }
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+/// Reports every candidate accepted by more than one of `layouts`.
+pub(crate) fn find_ambiguities<L, I>(layouts: &[L], candidates: I) -> Vec<Ambiguity>
+where
+    L: Layout,
+    I: IntoIterator<Item = Vec<u8>>,
+{
+    candidates
+        .into_iter()
+        .filter_map(|bytes| {
+            let accepted_by: Vec<_> = layouts
+                .iter()
+                .filter_map(|layout| Some((layout.label(), layout.decode_prefix(&bytes)?)))
+                .collect();
+            if accepted_by.len() > 1 {
+                Some(Ambiguity { bytes, accepted_by })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Searches encodings of the sample movies in `versions`, and variations of them, for
+/// byte strings that more than one of the versions accepts.
+pub fn check_versions(versions: &[MovieVersion]) -> Vec<Ambiguity> {
+    find_ambiguities(versions, candidates(versions, &sample_movies()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A version marker that, unlike `ENVELOPE_MARKER`, is a valid legacy title.
+    struct PrintableMarker;
+
+    const PRINTABLE_MARKER: [u8; 5] = [0x01, 0x00, 0x00, 0x00, b'M'];
+
+    impl Layout for PrintableMarker {
+        fn label(&self) -> String {
+            "PrintableMarker".into()
+        }
+
+        fn encode(&self, sample: &MovieV2) -> Vec<u8> {
+            let mut bytes = MovieVersion::V2.encode(sample);
+            bytes[..PRINTABLE_MARKER.len()].copy_from_slice(&PRINTABLE_MARKER);
+            bytes
+        }
+
+        fn decode_prefix(&self, bytes: &[u8]) -> Option<usize> {
+            let tag = MovieVersion::V2.as_u8();
+            let header = [&PRINTABLE_MARKER[..], &[tag]].concat();
+            if !bytes.starts_with(&header) {
+                return None;
+            }
+            let mut reader = Reader::at(bytes, header.len());
+            decode_tagged(&mut reader, MovieVersion::V2, tag, Mode::Lenient).ok()?;
+            Some(reader.offset())
+        }
+    }
+
+    #[test]
+    fn test_supported_versions_are_unambiguous() {
+        assert_eq!(check_versions(&MovieVersion::ALL), vec![]);
+    }
+
+    #[test]
+    fn test_tagged_headers_are_never_legacy_titles() {
+        for version in MovieVersion::ALL.into_iter().filter(|v| v.is_tagged()) {
+            let header = [&ENVELOPE_MARKER[..], &[version.as_u8()]].concat();
+            // Read as V1, the header starts with the title's length and contents. If the
+            // title ends inside the header and is not UTF-8, no record with it is V1.
+            let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+            let title = &header[4..];
+            assert!(len <= title.len(), "{version:?}");
+            assert!(std::str::from_utf8(&title[..len]).is_err(), "{version:?}");
+        }
+    }
+
+    #[test]
+    fn test_layouts_accept_their_own_encodings() {
+        for version in MovieVersion::ALL {
+            for sample in sample_movies() {
+                let encoded = version.encode(&sample);
+                assert_eq!(version.decode_prefix(&encoded), Some(encoded.len()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_detects_colliding_marker() {
+        let v1: Box<dyn Layout> = Box::new(MovieVersion::V1);
+        let layouts = [v1, Box::new(PrintableMarker)];
+        let ambiguities = find_ambiguities(&layouts, candidates(&layouts, &sample_movies()));
+        assert!(!ambiguities.is_empty());
+        let ambiguity = &ambiguities[0];
+        assert_eq!(ambiguity.accepted_by[0].0, "V1");
+        assert_eq!(ambiguity.accepted_by[1].0, "PrintableMarker");
+    }
+}
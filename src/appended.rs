@@ -0,0 +1,113 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use std::io;
+
+/// A struct field that was appended after records without it had already been written.
+///
+/// Unlike `Option<T>`, absence is encoded as nothing at all, so a record that ends
+/// right before this field decodes as [`Appended::Absent`] instead of failing with
+/// "Unexpected length of input". Presence is only detected by whether input remains,
+/// so appended fields must come last in a struct that is itself the last thing in the
+/// buffer (never inside a `Vec` or another struct), and an `Absent` field may only be
+/// followed by other `Absent` fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Appended<T> {
+    #[default]
+    Absent,
+    Present(T),
+}
+
+impl<T> Appended<T> {
+    pub fn as_ref(&self) -> Option<&T> {
+        match self {
+            Self::Absent => None,
+            Self::Present(value) => Some(value),
+        }
+    }
+
+    pub fn into_option(self) -> Option<T> {
+        match self {
+            Self::Absent => None,
+            Self::Present(value) => Some(value),
+        }
+    }
+
+    /// The value, or `legacy` for records written before the field existed.
+    pub fn unwrap_or_else<F: FnOnce() -> T>(self, legacy: F) -> T {
+        self.into_option().unwrap_or_else(legacy)
+    }
+
+    pub fn is_present(&self) -> bool {
+        matches!(self, Self::Present(_))
+    }
+}
+
+impl<T: Default> Appended<T> {
+    pub fn unwrap_or_default(self) -> T {
+        self.into_option().unwrap_or_default()
+    }
+}
+
+impl<T> From<T> for Appended<T> {
+    fn from(value: T) -> Self {
+        Self::Present(value)
+    }
+}
+
+impl<T: BorshSerialize> BorshSerialize for Appended<T> {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            Self::Absent => Ok(()),
+            Self::Present(value) => value.serialize(writer),
+        }
+    }
+}
+
+impl<T: BorshDeserialize> BorshDeserialize for Appended<T> {
+    fn deserialize(buf: &mut &[u8]) -> io::Result<Self> {
+        if buf.is_empty() {
+            return Ok(Self::Absent);
+        }
+        T::deserialize(buf).map(Self::Present)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::golden::back_to_the_future;
+    use crate::{Genre, MovieVersion};
+
+    #[derive(Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+    struct Movie {
+        title: String,
+        genre: Genre,
+        imdb_url: Appended<String>,
+    }
+
+    #[test]
+    fn test_absent_at_end_of_input() {
+        let input = back_to_the_future(MovieVersion::V1).bytes;
+        let movie = Movie::try_from_slice(&input).unwrap();
+        assert_eq!(movie.imdb_url, Appended::Absent);
+        assert_eq!(movie.try_to_vec().unwrap(), input);
+        assert_eq!(movie.imdb_url.unwrap_or_default(), "");
+    }
+
+    #[test]
+    fn test_present_round_trip() {
+        let movie = Movie {
+            title: "Back To The Future".into(),
+            genre: Genre::ScienceFiction,
+            imdb_url: String::from("https://www.imdb.com/title/tt0088763/").into(),
+        };
+        let serialized_movie = movie.try_to_vec().unwrap();
+        assert_eq!(Movie::try_from_slice(&serialized_movie).unwrap(), movie);
+    }
+
+    #[test]
+    fn test_truncated_field_is_an_error() {
+        let mut input = back_to_the_future(MovieVersion::V1).bytes;
+        input.extend_from_slice(&[0x05, 0x00]);
+        assert!(Movie::try_from_slice(&input).is_err());
+    }
+}
@@ -0,0 +1,298 @@
+//! Command-line tools for encoded `Movie` records.
+//!
+//! ```text
+//! movie inspect [--encoding hex|base64|base58] [RECORD...]
+//! movie decode [--encoding hex|base64|base58] [RECORD...]
+//! movie encode [--encoding hex|base64|base58] [--version N] [JSON...]
+//! movie verify [--encoding hex|base64|base58 | --framed] [FILE]
+//! movie migrate [--encoding hex|base64|base58 | --framed] [--dry-run] INPUT [OUTPUT]
+//! ```
+//!
+//! Inputs are read from the arguments, or one per line from stdin if there are none.
+//! Records are hex unless `--encoding` says otherwise. `encode` takes the JSON that
+//! `decode` prints. `verify` streams a dump of records, one per line or framed by
+//! their `u32` length with `--framed`, from `FILE` or stdin. `migrate` rewrites such a
+//! dump into `OUTPUT` in the latest version, or only reports what it would write.
+
+use borsh::BorshSerialize;
+use borsh_backwards_compatibility_example::cli::{movie_from_json, movie_to_json, Encoding};
+use borsh_backwards_compatibility_example::dump::{Format, Records};
+use borsh_backwards_compatibility_example::inspect::inspect;
+use borsh_backwards_compatibility_example::migrate::migrate;
+use borsh_backwards_compatibility_example::verify::verify;
+use borsh_backwards_compatibility_example::{Movie, MovieVersion};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str = "\
+usage: movie inspect [--encoding hex|base64|base58] [RECORD...]
+       movie decode [--encoding hex|base64|base58] [RECORD...]
+       movie encode [--encoding hex|base64|base58] [--version N] [JSON...]
+       movie verify [--encoding hex|base64|base58 | --framed] [FILE]
+       movie migrate [--encoding hex|base64|base58 | --framed] [--dry-run] INPUT [OUTPUT]";
+
+/// A record's bytes, or why its text is not valid in the chosen encoding.
+type Decoded = Result<Vec<u8>, String>;
+
+/// The options and inputs of a command.
+struct Input {
+    encoding: Encoding,
+    version: Option<MovieVersion>,
+    framed: bool,
+    dry_run: bool,
+    args: Vec<String>,
+}
+
+impl Input {
+    /// Parses `args`, accepting only the options in `allowed`.
+    fn parse(args: &[String], allowed: &[&str]) -> Result<Self, String> {
+        let mut encoding = Encoding::Hex;
+        let mut version = None;
+        let mut framed = false;
+        let mut dry_run = false;
+        let mut positional = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg.starts_with("--") && !allowed.contains(&arg.as_str()) {
+                return Err(format!("unknown option {arg}"));
+            }
+            let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--encoding" => encoding = value()?.parse()?,
+                "--version" => {
+                    let tag = value()?;
+                    version = Some(
+                        tag.parse()
+                            .ok()
+                            .and_then(MovieVersion::from_u8)
+                            .ok_or(format!("unknown version {tag}"))?,
+                    );
+                }
+                "--framed" => framed = true,
+                "--dry-run" => dry_run = true,
+                arg => positional.push(arg.to_string()),
+            }
+        }
+        Ok(Self {
+            encoding,
+            version,
+            framed,
+            dry_run,
+            args: positional,
+        })
+    }
+
+    /// The inputs given as arguments, or else the non-empty lines of stdin.
+    fn records(&self) -> Result<Vec<String>, String> {
+        if !self.args.is_empty() {
+            return Ok(self.args.clone());
+        }
+        stdin_lines().map_err(|err| format!("reading stdin: {err}"))
+    }
+
+    /// Each record with its decoded bytes.
+    fn bytes(&self) -> Result<Vec<(String, Decoded)>, String> {
+        let records = self.records()?;
+        Ok(records
+            .into_iter()
+            .map(|record| {
+                let bytes = self.encoding.decode(&record);
+                (record, bytes)
+            })
+            .collect())
+    }
+
+    fn format(&self) -> Format {
+        match self.framed {
+            true => Format::Framed,
+            false => Format::Lines(self.encoding),
+        }
+    }
+
+    /// The records of the dump named by the only argument, or of stdin.
+    fn dump(&self) -> Result<Records<Box<dyn BufRead>>, String> {
+        let reader: Box<dyn BufRead> = match self.args.as_slice() {
+            [] => Box::new(io::stdin().lock()),
+            [path] => Box::new(open(path)?),
+            _ => return Err("expected a single dump file".into()),
+        };
+        Ok(Records::new(reader, self.format()))
+    }
+}
+
+fn open(path: &str) -> Result<BufReader<File>, String> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|err| format!("{path}: {err}"))
+}
+
+/// The non-empty lines of stdin.
+fn stdin_lines() -> io::Result<Vec<String>> {
+    let mut lines = Vec::new();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            lines.push(line.trim().to_string());
+        }
+    }
+    Ok(lines)
+}
+
+fn run_inspect(args: &[String]) -> Result<(), String> {
+    let input = Input::parse(args, &["--encoding"])?;
+    for (i, (record, bytes)) in input.bytes()?.into_iter().enumerate() {
+        let bytes = bytes.map_err(|err| format!("{record:?}: {err}"))?;
+        if i > 0 {
+            println!();
+        }
+        print!("{}", inspect(&bytes));
+    }
+    Ok(())
+}
+
+/// Prints every record as a line of JSON. Records that do not decode are reported on
+/// stderr without stopping the others.
+fn run_decode(args: &[String]) -> Result<(), String> {
+    let input = Input::parse(args, &["--encoding"])?;
+    let records = input.bytes()?;
+    let mut failures = 0;
+    for (record, bytes) in &records {
+        let movie = bytes
+            .as_ref()
+            .map_err(String::clone)
+            .and_then(|bytes| Movie::try_from_slice(bytes).map_err(|err| err.to_string()));
+        match movie {
+            Ok(movie) => println!("{}", movie_to_json(&movie)),
+            Err(err) => {
+                eprintln!("{record:?}: {err}");
+                failures += 1;
+            }
+        }
+    }
+    match failures {
+        0 => Ok(()),
+        _ => Err(format!(
+            "{} of {} records failed to decode",
+            failures,
+            records.len()
+        )),
+    }
+}
+
+/// Prints the encoding of every JSON movie, in the requested version.
+fn run_encode(args: &[String]) -> Result<(), String> {
+    let input = Input::parse(args, &["--encoding", "--version"])?;
+    for record in &input.records()? {
+        let movie = serde_json::from_str(record)
+            .map_err(|err| err.to_string())
+            .and_then(|value| movie_from_json(&value, input.version))
+            .map_err(|err| format!("{record}: {err}"))?;
+        let bytes = movie.try_to_vec().expect("writing to a Vec cannot fail");
+        println!("{}", input.encoding.encode(&bytes));
+    }
+    Ok(())
+}
+
+/// Decodes and re-encodes every record of a dump, and fails unless all round trip.
+fn run_verify(args: &[String]) -> Result<(), String> {
+    let input = Input::parse(args, &["--encoding", "--framed"])?;
+    let report = verify(input.dump()?).map_err(|err| format!("reading dump: {err}"))?;
+    print!("{report}");
+    match report.is_ok() {
+        true => Ok(()),
+        false => Err(format!(
+            "{} of {} records failed verification",
+            report.failures.len(),
+            report.records
+        )),
+    }
+}
+
+/// Whether `source` and `target` name the same file, through any path, symlink or
+/// hard link. A `target` that does not exist yet is never the same.
+fn same_file(source: &Path, target: &Path) -> io::Result<bool> {
+    let source_meta = fs::metadata(source)?;
+    let target_meta = match fs::metadata(target) {
+        Ok(meta) => meta,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err),
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let _ = (source, target);
+        Ok(source_meta.dev() == target_meta.dev() && source_meta.ino() == target_meta.ino())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (source_meta, target_meta);
+        Ok(fs::canonicalize(source)? == fs::canonicalize(target)?)
+    }
+}
+
+/// A path next to `target` to write to before renaming it over `target`.
+fn temp_path(target: &Path) -> PathBuf {
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    target.with_file_name(format!(".{}.{}.tmp", name, process::id()))
+}
+
+/// Rewrites a dump in the latest version and prints a summary with checksums. The
+/// output is written to a temporary file that only replaces `OUTPUT` once migration
+/// succeeds, so a failed run leaves it as it was.
+fn run_migrate(args: &[String]) -> Result<(), String> {
+    let input = Input::parse(args, &["--encoding", "--framed", "--dry-run"])?;
+    let (source, target) = match (input.args.as_slice(), input.dry_run) {
+        ([source], true) => (source, None),
+        ([source, target], _) => (source, Some(target)),
+        _ => return Err(USAGE.to_string()),
+    };
+    let reader = open(source)?;
+    let summary = match target.filter(|_| !input.dry_run) {
+        None => migrate(reader, io::sink(), input.format()),
+        Some(target) => {
+            let same = same_file(Path::new(source), Path::new(target))
+                .map_err(|err| format!("{source}: {err}"))?;
+            if same {
+                return Err("refusing to migrate a dump in place".into());
+            }
+            let temp = temp_path(Path::new(target));
+            let file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&temp)
+                .map_err(|err| format!("{}: {}", temp.display(), err))?;
+            let summary = migrate(reader, BufWriter::new(&file), input.format())
+                .and_then(|summary| file.sync_all().map(|()| summary))
+                .and_then(|summary| fs::rename(&temp, target).map(|()| summary));
+            if summary.is_err() {
+                let _ = fs::remove_file(&temp);
+            }
+            summary
+        }
+    }
+    .map_err(|err| format!("{source}: {err}"))?;
+    print!("{summary}");
+    if input.dry_run {
+        println!("dry run, nothing written");
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("inspect") => run_inspect(&args[1..]),
+        Some("decode") => run_decode(&args[1..]),
+        Some("encode") => run_encode(&args[1..]),
+        Some("verify") => run_verify(&args[1..]),
+        Some("migrate") => run_migrate(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    };
+    if let Err(err) = result {
+        eprintln!("{err}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,187 @@
+//! Text formats used by the `movie` binary, kept here so they can be tested.
+
+use crate::{Movie, MovieVersion};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// How encoded records are written as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    Hex,
+    Base64,
+    Base58,
+}
+
+impl Encoding {
+    pub const ALL: [Self; 3] = [Self::Hex, Self::Base64, Self::Base58];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Hex => "hex",
+            Self::Base64 => "base64",
+            Self::Base58 => "base58",
+        }
+    }
+
+    pub fn decode(self, text: &str) -> Result<Vec<u8>, String> {
+        let text = text.trim();
+        match self {
+            Self::Hex => hex::decode(text).map_err(|err| err.to_string()),
+            Self::Base64 => BASE64.decode(text).map_err(|err| err.to_string()),
+            Self::Base58 => bs58::decode(text).into_vec().map_err(|err| err.to_string()),
+        }
+        .map_err(|err| format!("invalid {self}: {err}"))
+    }
+
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            Self::Hex => hex::encode(bytes),
+            Self::Base64 => BASE64.encode(bytes),
+            Self::Base58 => bs58::encode(bytes).into_string(),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|encoding| encoding.name() == name)
+            .ok_or_else(|| format!("unknown encoding {name:?}, expected hex, base64 or base58"))
+    }
+}
+
+/// A movie with the version it was written in, as printed by the `movie` binary.
+#[derive(Serialize)]
+struct Versioned<'a> {
+    version: u8,
+    #[serde(flatten)]
+    movie: &'a Movie,
+}
+
+/// `movie` as a line of JSON, with the version it was written in. `imdb_url` is left
+/// out for records that predate it.
+pub fn movie_to_json(movie: &Movie) -> String {
+    serde_json::to_string(&Versioned {
+        version: movie.version().as_u8(),
+        movie,
+    })
+    .expect("a Movie always serializes")
+}
+
+/// Reads a movie in the format of [`movie_to_json`], to be written with `version`. If
+/// that is `None`, the object's own `version` is used, or else the latest version.
+pub fn movie_from_json(value: &Value, version: Option<MovieVersion>) -> Result<Movie, String> {
+    let mut object = value.as_object().ok_or("expected a JSON object")?.clone();
+    let version = match (version, object.remove("version")) {
+        (Some(version), _) => version,
+        (None, None) => MovieVersion::LATEST,
+        (None, Some(value)) => value
+            .as_u64()
+            .and_then(|tag| u8::try_from(tag).ok())
+            .and_then(MovieVersion::from_u8)
+            .ok_or_else(|| format!("unknown version {value}"))?,
+    };
+    let movie: Movie =
+        serde_json::from_value(Value::Object(object)).map_err(|err| err.to_string())?;
+    match (version, movie) {
+        (MovieVersion::V1, Movie::V2(_)) => Err("version 1 has no imdb_url".into()),
+        (MovieVersion::V2, Movie::V1(_)) => Err("missing field `imdb_url`".into()),
+        (_, movie) => Ok(movie),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::golden::back_to_the_future;
+    use crate::{Genre, MovieV1};
+    use borsh::BorshSerialize;
+    use serde_json::json;
+
+    #[test]
+    fn test_encodings_round_trip() {
+        let bytes = back_to_the_future(MovieVersion::V1).bytes;
+        for encoding in Encoding::ALL {
+            let text = encoding.encode(&bytes);
+            assert_eq!(encoding.decode(&text).unwrap(), bytes, "{encoding}");
+            assert_eq!(encoding.name().parse::<Encoding>().unwrap(), encoding);
+        }
+        assert_eq!(Encoding::Base64.encode(&[0xff, 0x00]), "/wA=");
+        assert_eq!(Encoding::Base58.encode(&[0x00, 0x01]), "12");
+        assert!(Encoding::Hex.decode("zz").is_err());
+        assert!("base32".parse::<Encoding>().is_err());
+    }
+
+    #[test]
+    fn test_movie_to_json() {
+        let legacy = Movie::V1(MovieV1 {
+            title: "Back To The Future".into(),
+            genre: Genre::ScienceFiction,
+        });
+        assert_eq!(
+            movie_to_json(&legacy),
+            r#"{"version":1,"title":"Back To The Future","genre":"science_fiction"}"#
+        );
+        let movie = Movie::new("Up".into(), Genre::from_u8(42), "u".into());
+        assert_eq!(
+            movie_to_json(&movie),
+            r#"{"version":2,"title":"Up","genre":42,"imdb_url":"u"}"#
+        );
+    }
+
+    #[test]
+    fn test_movie_from_json() {
+        let value = json!({
+            "title": "Back To The Future",
+            "genre": "science_fiction",
+            "imdb_url": "https://www.imdb.com/title/tt0088763/",
+        });
+        let movie = movie_from_json(&value, None).unwrap();
+        assert_eq!(movie.version(), MovieVersion::LATEST);
+        assert_eq!(movie.genre(), &Genre::ScienceFiction);
+        let mut legacy = value.clone();
+        legacy.as_object_mut().unwrap().remove("imdb_url");
+        let legacy = movie_from_json(&legacy, Some(MovieVersion::V1)).unwrap();
+        assert_eq!(
+            legacy.try_to_vec().unwrap(),
+            back_to_the_future(MovieVersion::V1).bytes
+        );
+        assert_eq!(
+            movie_from_json(&value, Some(MovieVersion::V1)).unwrap_err(),
+            "version 1 has no imdb_url"
+        );
+        assert_eq!(
+            movie_from_json(&json!({"title": "Up", "genre": "western"}), None).unwrap_err(),
+            "unknown genre \"western\""
+        );
+        assert!(movie_from_json(&json!({"title": "Up", "genre": 0, "rating": 5}), None).is_err());
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let movies = [
+            Movie::V1(MovieV1 {
+                title: "Groundhog Day".into(),
+                genre: Genre::Comedy,
+            }),
+            Movie::new("Up".into(), Genre::from_u8(42), "u".into()),
+        ];
+        for movie in movies {
+            let value = serde_json::from_str(&movie_to_json(&movie)).unwrap();
+            assert_eq!(movie_from_json(&value, None).unwrap(), movie);
+        }
+    }
+}
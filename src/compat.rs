@@ -0,0 +1,148 @@
+//! Cross-version compatibility matrix: sample movies are written by every supported
+//! version and read back by a node built for every supported version.
+
+use crate::ambiguity::Layout;
+use crate::{Movie, MovieV1, MovieV2, MovieVersion};
+use borsh::{BorshDeserialize, BorshSerialize};
+use std::fmt;
+use std::io;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Outcome {
+    /// Read back as exactly the value that was written.
+    Ok,
+    /// Read back as the value that was written, in a layout older than the reader's
+    /// own, and upgraded with [`Movie::into_latest`] to a movie that keeps its fields
+    /// and round trips in the latest layout.
+    Upgraded,
+    /// The reader refuses the record.
+    Rejected,
+    /// The reader accepted the record but decoded a different value. Always a bug.
+    Mismatch,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Self::Ok => "ok",
+            Self::Upgraded => "upgraded",
+            Self::Rejected => "rejected",
+            Self::Mismatch => "MISMATCH",
+        };
+        f.pad(label)
+    }
+}
+
+/// Decodes `bytes` the way a release whose newest layout is `reader` would.
+pub(crate) fn read_as(reader: MovieVersion, bytes: &[u8]) -> io::Result<Movie> {
+    match reader {
+        // The release before V2 only knew the original struct.
+        MovieVersion::V1 => MovieV1::try_from_slice(bytes).map(Movie::V1),
+        MovieVersion::V2 => Movie::try_from_slice(bytes).map_err(io::Error::from),
+    }
+}
+
+fn outcome(reader: MovieVersion, written: &Movie, bytes: &[u8]) -> Outcome {
+    match read_as(reader, bytes) {
+        Err(_) => Outcome::Rejected,
+        Ok(read) if &read != written => Outcome::Mismatch,
+        Ok(read) if read.version() < reader => upgrade(read),
+        Ok(_) => Outcome::Ok,
+    }
+}
+
+/// Upgrades a movie read in an older layout, as the reader does before using it.
+fn upgrade(read: Movie) -> Outcome {
+    let upgraded = Movie::V2(read.clone().into_latest());
+    let bytes = upgraded.try_to_vec().expect("writing to a Vec cannot fail");
+    let kept_fields = upgraded.title() == read.title() && upgraded.genre() == read.genre();
+    match Movie::try_from_slice(&bytes) {
+        Ok(again) if kept_fields && again == upgraded => Outcome::Upgraded,
+        _ => Outcome::Mismatch,
+    }
+}
+
+/// `outcomes[writer][reader]`, indexed like [`MovieVersion::ALL`]. Each cell holds the
+/// worst outcome over all samples.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Matrix {
+    pub outcomes: Vec<Vec<Outcome>>,
+}
+
+impl Matrix {
+    pub fn get(&self, writer: MovieVersion, reader: MovieVersion) -> Outcome {
+        let index = |version| {
+            MovieVersion::ALL
+                .iter()
+                .position(|other| *other == version)
+                .unwrap()
+        };
+        self.outcomes[index(writer)][index(reader)]
+    }
+}
+
+pub fn compatibility_matrix(samples: &[MovieV2]) -> Matrix {
+    let outcomes = MovieVersion::ALL
+        .iter()
+        .map(|writer| {
+            MovieVersion::ALL
+                .iter()
+                .map(|reader| {
+                    samples
+                        .iter()
+                        .map(|sample| {
+                            let bytes = writer.encode(sample);
+                            let written = Movie::try_from_slice(&bytes)
+                                .expect("every version reads what it writes");
+                            outcome(*reader, &written, &bytes)
+                        })
+                        .max()
+                        .unwrap_or(Outcome::Ok)
+                })
+                .collect()
+        })
+        .collect();
+    Matrix { outcomes }
+}
+
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let row = |label: String, cells: Vec<String>| {
+            let cells: Vec<_> = cells.iter().map(|cell| format!("{cell:<8}")).collect();
+            format!("{:<15} | {}", label, cells.join(" | "))
+                .trim_end()
+                .to_string()
+        };
+        let readers = MovieVersion::ALL.iter().map(|reader| format!("{reader:?}"));
+        writeln!(f, "{}", row("writer \\ reader".into(), readers.collect()))?;
+        for (writer, outcomes) in MovieVersion::ALL.iter().zip(&self.outcomes) {
+            let cells = outcomes.iter().map(Outcome::to_string).collect();
+            writeln!(f, "{}", row(format!("{writer:?}"), cells))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ambiguity::sample_movies;
+
+    #[test]
+    fn test_compatibility_matrix() {
+        let matrix = compatibility_matrix(&sample_movies());
+        println!("{matrix}");
+        assert_eq!(
+            matrix.to_string(),
+            "\
+writer \\ reader | V1       | V2\n\
+V1              | ok       | upgraded\n\
+V2              | rejected | ok\n",
+            "\n{matrix}"
+        );
+        assert_eq!(
+            matrix.get(MovieVersion::V1, MovieVersion::LATEST),
+            Outcome::Upgraded
+        );
+    }
+}
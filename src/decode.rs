@@ -0,0 +1,456 @@
+//! Field-by-field decoding of [`Movie`], reporting where and why a record is invalid.
+//!
+//! Both the lenient decoder behind `BorshDeserialize` and the strict one in
+//! [`Movie::try_from_slice_strict`] are built on [`Reader`], which tracks the byte
+//! offset of every field it reads.
+
+use crate::inspect::Segment;
+use crate::{Genre, Movie, MovieV1, MovieV2, MovieVersion, ENVELOPE_MARKER};
+use borsh::BorshDeserialize;
+use std::fmt;
+use std::io;
+
+/// The part of an encoded `Movie` that failed to decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field {
+    Version,
+    Title,
+    Genre,
+    /// The extension block as a whole, including its length prefix.
+    Extension,
+    ImdbUrl,
+    /// Whatever follows a complete record.
+    End,
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Version => "version",
+            Self::Title => "title",
+            Self::Genre => "genre",
+            Self::Extension => "extension",
+            Self::ImdbUrl => "imdb_url",
+            Self::End => "end of record",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeErrorKind {
+    /// The field needs more bytes than are left.
+    UnexpectedEnd {
+        needed: usize,
+        available: usize,
+    },
+    InvalidUtf8,
+    /// A version tag that was never assigned to a tagged layout.
+    UnknownVersion(u8),
+    /// A version newer than this build, only rejected when decoding strictly.
+    NewerVersion(u8),
+    /// A genre this build does not know, only rejected when decoding strictly.
+    UnknownGenre(u8),
+    /// The extension block holds this many bytes beyond the fields of its version.
+    ExtensionTooLong(usize),
+    /// This many bytes follow a complete record.
+    TrailingBytes(usize),
+}
+
+impl fmt::Display for DecodeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd { needed, available } => {
+                write!(f, "needs {needed} bytes but only {available} are left")
+            }
+            Self::InvalidUtf8 => f.write_str("not valid UTF-8"),
+            Self::UnknownVersion(tag) => write!(f, "unknown version tag {tag}"),
+            Self::NewerVersion(tag) => write!(f, "version {tag} is newer than this build"),
+            Self::UnknownGenre(byte) => write!(f, "unknown genre {byte}"),
+            Self::ExtensionTooLong(len) => write!(f, "{len} unexpected bytes"),
+            Self::TrailingBytes(len) => write!(f, "{len} trailing bytes"),
+        }
+    }
+}
+
+/// Why an encoded `Movie` was rejected, and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    pub field: Field,
+    /// Offset of the offending bytes from the start of the record.
+    pub offset: usize,
+    pub kind: DecodeErrorKind,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Movie {} at byte {}: {}",
+            self.field, self.offset, self.kind
+        )
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<DecodeError> for io::Error {
+    fn from(err: DecodeError) -> Self {
+        let kind = match err.kind {
+            // Borsh reports running out of input as `InvalidInput`.
+            DecodeErrorKind::UnexpectedEnd { .. } => io::ErrorKind::InvalidInput,
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+/// How much of what [`Movie`]'s encoding can express a decoder accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mode {
+    /// Keeps unknown genres and skips the unknown extension fields of newer versions.
+    Lenient,
+    /// Only accepts what this build would write itself.
+    Strict,
+}
+
+/// A cursor over an encoded record that knows the offset of every byte it reads.
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    /// Labelled byte ranges, only collected for [`crate::inspect::inspect`].
+    segments: Option<Vec<Segment>>,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self::at(bytes, 0)
+    }
+
+    /// A reader that starts `offset` bytes into `bytes`.
+    pub(crate) fn at(bytes: &'a [u8], offset: usize) -> Self {
+        Self {
+            bytes,
+            offset,
+            segments: None,
+        }
+    }
+
+    /// A reader that also labels the byte range of every field it reads.
+    pub(crate) fn recording(bytes: &'a [u8]) -> Self {
+        Self {
+            segments: Some(Vec::new()),
+            ..Self::new(bytes)
+        }
+    }
+
+    pub(crate) fn into_segments(self) -> Vec<Segment> {
+        self.segments.unwrap_or_default()
+    }
+
+    /// Labels the bytes from `start` up to the current offset. `label` is only
+    /// evaluated when recording.
+    fn note(&mut self, start: usize, label: impl FnOnce() -> String) {
+        if let Some(segments) = &mut self.segments {
+            segments.push(Segment {
+                range: start..self.offset,
+                label: label(),
+            });
+        }
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub(crate) fn rest(&self) -> &'a [u8] {
+        &self.bytes[self.offset..]
+    }
+
+    fn error(&self, field: Field, kind: DecodeErrorKind) -> DecodeError {
+        DecodeError {
+            field,
+            offset: self.offset,
+            kind,
+        }
+    }
+
+    fn take(&mut self, field: Field, len: usize) -> Result<&'a [u8], DecodeError> {
+        let available = self.rest().len();
+        if available < len {
+            return Err(self.error(
+                field,
+                DecodeErrorKind::UnexpectedEnd {
+                    needed: len,
+                    available,
+                },
+            ));
+        }
+        let bytes = &self.rest()[..len];
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self, field: Field) -> Result<u8, DecodeError> {
+        Ok(self.take(field, 1)?[0])
+    }
+
+    fn read_u32(&mut self, field: Field) -> Result<u32, DecodeError> {
+        let bytes = self.take(field, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// A length-prefixed byte string, as Borsh writes `Vec<u8>`.
+    fn read_bytes(&mut self, field: Field) -> Result<&'a [u8], DecodeError> {
+        let start = self.offset;
+        let len = self.read_u32(field)? as usize;
+        self.note(start, || format!("{field} length {len}"));
+        self.take(field, len)
+    }
+
+    fn read_string(&mut self, field: Field) -> Result<String, DecodeError> {
+        let bytes = self.read_bytes(field)?;
+        let start = self.offset - bytes.len();
+        let string = String::from_utf8(bytes.to_vec()).map_err(|err| DecodeError {
+            field,
+            offset: start + err.utf8_error().valid_up_to(),
+            kind: DecodeErrorKind::InvalidUtf8,
+        })?;
+        self.note(start, || format!("{field} {string:?}"));
+        Ok(string)
+    }
+
+    fn read_genre(&mut self, mode: Mode) -> Result<Genre, DecodeError> {
+        let byte = self.read_u8(Field::Genre)?;
+        self.note(self.offset - 1, || {
+            format!("genre Genre::{:?}", Genre::from_u8(byte))
+        });
+        match Genre::from_u8(byte) {
+            Genre::Unknown(unknown) if mode == Mode::Strict => Err(DecodeError {
+                field: Field::Genre,
+                offset: self.offset - 1,
+                kind: DecodeErrorKind::UnknownGenre(unknown.as_u8()),
+            }),
+            genre => Ok(genre),
+        }
+    }
+
+    /// Reads the envelope marker and version tag, if the record at the reader's position
+    /// has them. `None` for an untagged V1 record.
+    fn read_tag(&mut self) -> Result<Option<u8>, DecodeError> {
+        if !self.rest().starts_with(&ENVELOPE_MARKER) {
+            return Ok(None);
+        }
+        self.offset += ENVELOPE_MARKER.len();
+        self.note(self.offset - ENVELOPE_MARKER.len(), || {
+            "envelope marker".into()
+        });
+        self.read_u8(Field::Version).map(Some)
+    }
+
+    /// Fails unless every byte has been read.
+    pub(crate) fn finish(&self) -> Result<(), DecodeError> {
+        match self.rest().len() {
+            0 => Ok(()),
+            len => Err(self.error(Field::End, DecodeErrorKind::TrailingBytes(len))),
+        }
+    }
+}
+
+/// The version tag of the encoded `Movie` at the start of `bytes`, or `None` for an
+/// untagged V1 record. Tags of versions newer than this build are returned as is, and
+/// are decoded with [`MovieVersion::LATEST`].
+pub fn read_tag(bytes: &[u8]) -> Result<Option<u8>, DecodeError> {
+    Reader::new(bytes).read_tag()
+}
+
+/// Decodes the record that starts at the reader's position.
+pub(crate) fn decode_movie(reader: &mut Reader, mode: Mode) -> Result<Movie, DecodeError> {
+    let tag = match reader.read_tag()? {
+        Some(tag) => tag,
+        None => {
+            let title = reader.read_string(Field::Title)?;
+            let genre = reader.read_genre(mode)?;
+            return Ok(Movie::V1(MovieV1 { title, genre }));
+        }
+    };
+    let tag_offset = reader.offset - 1;
+    let tag_error = |kind| DecodeError {
+        field: Field::Version,
+        offset: tag_offset,
+        kind,
+    };
+    let newer = tag > MovieVersion::LATEST.as_u8();
+    let version = match MovieVersion::from_u8(tag) {
+        Some(version) if version.is_tagged() => version,
+        None if newer && mode == Mode::Lenient => MovieVersion::LATEST,
+        None if newer => return Err(tag_error(DecodeErrorKind::NewerVersion(tag))),
+        _ => return Err(tag_error(DecodeErrorKind::UnknownVersion(tag))),
+    };
+    reader.note(tag_offset, || match MovieVersion::from_u8(tag) {
+        Some(version) => format!("version tag {tag} = {version:?}"),
+        None => format!("version tag {tag}, newer than {version:?}"),
+    });
+    decode_tagged(reader, version, tag, mode)
+}
+
+/// Decodes the core fields and extension block that follow the header of a record
+/// tagged `tag`, reading them with the layout of `version`.
+pub(crate) fn decode_tagged(
+    reader: &mut Reader,
+    version: MovieVersion,
+    tag: u8,
+    mode: Mode,
+) -> Result<Movie, DecodeError> {
+    let title = reader.read_string(Field::Title)?;
+    let genre = reader.read_genre(mode)?;
+    let extension = reader.read_bytes(Field::Extension)?;
+    let mut extension = Reader {
+        bytes: &reader.bytes[..reader.offset],
+        offset: reader.offset - extension.len(),
+        segments: reader.segments.take(),
+    };
+    let movie = decode_extension(&mut extension, title, genre, version, tag, mode);
+    reader.segments = extension.segments;
+    movie
+}
+
+/// Decodes the fields `version` added to the extension block.
+fn decode_extension(
+    extension: &mut Reader,
+    title: String,
+    genre: Genre,
+    version: MovieVersion,
+    tag: u8,
+    mode: Mode,
+) -> Result<Movie, DecodeError> {
+    let movie = match version {
+        MovieVersion::V1 => unreachable!("V1 records are never tagged"),
+        MovieVersion::V2 => Movie::V2(MovieV2 {
+            title,
+            genre,
+            imdb_url: extension.read_string(Field::ImdbUrl)?,
+        }),
+    };
+    let unknown = extension.rest().len();
+    if unknown > 0 && (tag == version.as_u8() || mode == Mode::Strict) {
+        return Err(extension.error(Field::Extension, DecodeErrorKind::ExtensionTooLong(unknown)));
+    }
+    if unknown > 0 {
+        let start = extension.offset;
+        extension.offset += unknown;
+        extension.note(start, || {
+            "skipped extension fields of a newer version".into()
+        });
+    }
+    Ok(movie)
+}
+
+impl Movie {
+    /// Decodes a single record that fills all of `bytes`. Unlike the `BorshDeserialize`
+    /// method of the same name, failures say which field is invalid and where.
+    pub fn try_from_slice(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes);
+        let movie = decode_movie(&mut reader, Mode::Lenient)?;
+        reader.finish()?;
+        Ok(movie)
+    }
+}
+
+impl BorshDeserialize for Movie {
+    fn deserialize(buf: &mut &[u8]) -> io::Result<Self> {
+        let mut reader = Reader::new(buf);
+        let movie = decode_movie(&mut reader, Mode::Lenient)?;
+        *buf = reader.rest();
+        Ok(movie)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::golden::back_to_the_future;
+
+    fn error(field: Field, offset: usize, kind: DecodeErrorKind) -> DecodeError {
+        DecodeError {
+            field,
+            offset,
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_reports_truncated_fields() {
+        let legacy = back_to_the_future(MovieVersion::V1).bytes;
+        assert_eq!(
+            Movie::try_from_slice(&legacy[..10]).unwrap_err(),
+            error(
+                Field::Title,
+                4,
+                DecodeErrorKind::UnexpectedEnd {
+                    needed: 18,
+                    available: 6
+                }
+            )
+        );
+        assert_eq!(
+            Movie::try_from_slice(&legacy[..22]).unwrap_err(),
+            error(
+                Field::Genre,
+                22,
+                DecodeErrorKind::UnexpectedEnd {
+                    needed: 1,
+                    available: 0
+                }
+            )
+        );
+        let tagged = back_to_the_future(MovieVersion::V2).bytes;
+        let err = Movie::try_from_slice(&tagged[..tagged.len() - 1]).unwrap_err();
+        assert_eq!(err.field, Field::Extension);
+        assert_eq!(err.offset, 6 + 22 + 1 + 4);
+    }
+
+    #[test]
+    fn test_reports_invalid_utf8_offset() {
+        let mut input = back_to_the_future(MovieVersion::V1).bytes;
+        input[9] = 0xff;
+        assert_eq!(
+            Movie::try_from_slice(&input).unwrap_err(),
+            error(Field::Title, 9, DecodeErrorKind::InvalidUtf8)
+        );
+        let mut input = back_to_the_future(MovieVersion::V2).bytes;
+        let last = input.len() - 1;
+        input[last] = 0xff;
+        assert_eq!(
+            Movie::try_from_slice(&input).unwrap_err(),
+            error(Field::ImdbUrl, last, DecodeErrorKind::InvalidUtf8)
+        );
+    }
+
+    #[test]
+    fn test_reports_trailing_bytes() {
+        let mut input = back_to_the_future(MovieVersion::V1).bytes;
+        input.push(0);
+        assert_eq!(
+            Movie::try_from_slice(&input).unwrap_err(),
+            error(Field::End, 23, DecodeErrorKind::TrailingBytes(1))
+        );
+    }
+
+    #[test]
+    fn test_io_error_wraps_decode_error() {
+        let legacy = back_to_the_future(MovieVersion::V1).bytes;
+        let err = <Movie as BorshDeserialize>::try_from_slice(&legacy[..10]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let inner = err
+            .get_ref()
+            .unwrap()
+            .downcast_ref::<DecodeError>()
+            .unwrap();
+        assert_eq!(inner.field, Field::Title);
+        assert_eq!(
+            err.to_string(),
+            "Movie title at byte 4: needs 18 bytes but only 6 are left"
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! Files of encoded `Movie` records, read one record at a time.
+//!
+//! A dump is either text, one record per line in an [`Encoding`], or binary, where
+//! every record is framed by its `u32` little-endian length like a Borsh `Vec<u8>`.
+
+use crate::cli::Encoding;
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Lines(Encoding),
+    Framed,
+}
+
+/// Where a record starts in its dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Location {
+    /// 1-based line number of a text dump.
+    Line(usize),
+    /// Byte offset of the length prefix in a framed dump.
+    Offset(u64),
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Line(line) => write!(f, "line {line}"),
+            Self::Offset(offset) => write!(f, "offset {offset}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub location: Location,
+    /// The record's bytes, or why its line is not valid text in the dump's encoding.
+    pub bytes: Result<Vec<u8>, String>,
+}
+
+/// Iterates over the records of a dump. Blank lines of text dumps are skipped.
+pub struct Records<R> {
+    reader: R,
+    format: Format,
+    /// Lines or bytes read so far.
+    position: u64,
+}
+
+impl<R: BufRead> Records<R> {
+    pub fn new(reader: R, format: Format) -> Self {
+        Self {
+            reader,
+            format,
+            position: 0,
+        }
+    }
+
+    /// Where the next record starts, or where a truncated one started after it failed.
+    pub fn location(&self) -> Location {
+        match self.format {
+            Format::Lines(_) => Location::Line(self.position as usize + 1),
+            Format::Framed => Location::Offset(self.position),
+        }
+    }
+
+    fn next_line(&mut self, encoding: Encoding) -> io::Result<Option<Record>> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            self.position += 1;
+            if !line.trim().is_empty() {
+                break;
+            }
+        }
+        Ok(Some(Record {
+            location: Location::Line(self.position as usize),
+            bytes: encoding.decode(&line),
+        }))
+    }
+
+    fn next_frame(&mut self) -> io::Result<Option<Record>> {
+        let location = Location::Offset(self.position);
+        let mut len = [0; 4];
+        let read = read_up_to(&mut self.reader, &mut len)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if read < len.len() {
+            return Err(truncated(location));
+        }
+        let len = u32::from_le_bytes(len) as u64;
+        // `take` rather than a preallocated buffer, so a corrupt length cannot make us
+        // allocate more than the file holds.
+        let mut bytes = Vec::new();
+        (&mut self.reader).take(len).read_to_end(&mut bytes)?;
+        if (bytes.len() as u64) < len {
+            return Err(truncated(location));
+        }
+        self.position += 4 + len;
+        Ok(Some(Record {
+            location,
+            bytes: Ok(bytes),
+        }))
+    }
+}
+
+/// Fills as much of `buf` as the reader has left.
+fn read_up_to(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(read)
+}
+
+fn truncated(location: Location) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        format!("Truncated record at {location}"),
+    )
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = match self.format {
+            Format::Lines(encoding) => self.next_line(encoding),
+            Format::Framed => self.next_frame(),
+        };
+        record.transpose()
+    }
+}
+
+/// Appends `bytes` to a dump in `format`.
+pub fn write_record<W: Write>(writer: &mut W, format: Format, bytes: &[u8]) -> io::Result<()> {
+    match format {
+        Format::Lines(encoding) => writeln!(writer, "{}", encoding.encode(bytes)),
+        Format::Framed => {
+            let len = u32::try_from(bytes.len())
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Record too long"))?;
+            writer.write_all(&len.to_le_bytes())?;
+            writer.write_all(bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(input: &[u8], format: Format) -> io::Result<Vec<Record>> {
+        Records::new(input, format).collect()
+    }
+
+    #[test]
+    fn test_read_lines() {
+        let records = read_all(b"0102\n\nzz\n03", Format::Lines(Encoding::Hex)).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].bytes, Ok(vec![1, 2]));
+        assert_eq!(records[1].location, Location::Line(3));
+        assert!(records[1].bytes.is_err());
+        assert_eq!(records[2].bytes, Ok(vec![3]));
+    }
+
+    #[test]
+    fn test_framed_round_trip() {
+        let mut dump = Vec::new();
+        for record in [&b""[..], b"\x01\x02", b"abc"] {
+            write_record(&mut dump, Format::Framed, record).unwrap();
+        }
+        let records = read_all(&dump, Format::Framed).unwrap();
+        let locations: Vec<_> = records.iter().map(|record| record.location).collect();
+        assert_eq!(
+            locations,
+            [
+                Location::Offset(0),
+                Location::Offset(4),
+                Location::Offset(10)
+            ]
+        );
+        assert_eq!(records[2].bytes, Ok(b"abc".to_vec()));
+    }
+
+    #[test]
+    fn test_framed_truncation() {
+        let err = read_all(&[3, 0, 0, 0, 1, 2], Format::Framed).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = read_all(&[3, 0], Format::Framed).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        // A hostile length is bounded by the input, not allocated up front.
+        let err = read_all(&[0xff, 0xff, 0xff, 0xff, 1], Format::Framed).unwrap_err();
+        assert_eq!(err.to_string(), "Truncated record at offset 0");
+    }
+}
@@ -0,0 +1,185 @@
+//! The wire format of [`Movie`].
+//!
+//! V1 records are written exactly as they always were: the Borsh encoding of
+//! [`MovieV1`], starting with the `u32` length of `title`. Every later version is
+//! written as [`ENVELOPE_MARKER`], a one byte version tag, the core fields `title`
+//! and `genre`, and an extension block holding everything added since V1:
+//!
+//! ```text
+//! 01000000 ff | 02  | title  genre | u32 length | imdb_url ...
+//!   marker    | tag |     core     |   extension block
+//! ```
+//!
+//! The two cannot be confused. Read as a V1 record, the marker says that `title` is
+//! one byte long and that this byte is `0xFF`. Borsh requires `String` contents to be
+//! valid UTF-8, and `0xFF` is not valid anywhere in UTF-8, so decoding `title` fails
+//! for every input that starts with the marker. Conversely every successfully decoded
+//! V1 record has a valid UTF-8 `title` and therefore does not start with the marker.
+//! Checking the first five bytes is enough to know which layout follows.
+//!
+//! New versions only append fields to the extension block, so its length prefix lets
+//! a node read records written by a newer release: it decodes the fields it knows
+//! and skips the rest of the block. A record tagged with a version this build knows
+//! must not carry any extra extension bytes.
+
+use crate::{Genre, Movie};
+use borsh::BorshSerialize;
+use std::io;
+
+/// Prefix of every tagged `Movie` encoding.
+///
+/// Read as a legacy record this is a `title` of length 1 consisting of the byte
+/// `0xFF`, which never occurs in UTF-8. No untagged record can therefore start with it.
+pub const ENVELOPE_MARKER: [u8; 5] = [0x01, 0x00, 0x00, 0x00, 0xFF];
+
+/// The layouts `Movie` has had, in the order they were introduced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MovieVersion {
+    V1 = 1,
+    V2 = 2,
+}
+
+impl MovieVersion {
+    pub const ALL: [Self; 2] = [Self::V1, Self::V2];
+    pub const LATEST: Self = Self::V2;
+
+    pub fn from_u8(tag: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|version| version.as_u8() == tag)
+    }
+
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+
+    /// V1 predates the envelope and is only ever written untagged.
+    pub fn is_tagged(self) -> bool {
+        self != Self::V1
+    }
+}
+
+impl Movie {
+    pub fn version(&self) -> MovieVersion {
+        match self {
+            Self::V1(_) => MovieVersion::V1,
+            Self::V2(_) => MovieVersion::V2,
+        }
+    }
+}
+
+fn serialize_tagged<W: io::Write>(
+    writer: &mut W,
+    version: MovieVersion,
+    title: &str,
+    genre: &Genre,
+    extension: &[u8],
+) -> io::Result<()> {
+    writer.write_all(&ENVELOPE_MARKER)?;
+    writer.write_all(&[version.as_u8()])?;
+    title.serialize(writer)?;
+    genre.serialize(writer)?;
+    extension.serialize(writer)
+}
+
+impl BorshSerialize for Movie {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            Self::V1(movie) => movie.serialize(writer),
+            Self::V2(movie) => serialize_tagged(
+                writer,
+                MovieVersion::V2,
+                &movie.title,
+                &movie.genre,
+                &movie.imdb_url.try_to_vec()?,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::golden::back_to_the_future;
+    use crate::{read_tag, DecodeErrorKind, Field, Genre, MovieV1};
+    use borsh::BorshDeserialize;
+
+    #[test]
+    fn test_marker_is_never_a_legacy_title() {
+        // A one byte title decodes exactly when that byte is ASCII.
+        for byte in 0..=u8::MAX {
+            let input = [0x01, 0x00, 0x00, 0x00, byte, 0x05];
+            assert_eq!(MovieV1::try_from_slice(&input).is_ok(), byte < 0x80);
+        }
+        // Whatever follows the marker, it cannot be read as a legacy record.
+        let legacy = back_to_the_future(MovieVersion::V1).bytes;
+        for end in 0..=legacy.len() {
+            let mut input = ENVELOPE_MARKER.to_vec();
+            input.extend_from_slice(&legacy[..end]);
+            assert!(MovieV1::deserialize(&mut input.as_slice()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_read_tag() {
+        let legacy = back_to_the_future(MovieVersion::V1).bytes;
+        assert_eq!(read_tag(&legacy).unwrap(), None);
+        let tagged = back_to_the_future(MovieVersion::V2).bytes;
+        assert_eq!(tagged[..5], ENVELOPE_MARKER);
+        assert_eq!(read_tag(&tagged).unwrap(), Some(MovieVersion::V2.as_u8()));
+        let err = read_tag(&ENVELOPE_MARKER).unwrap_err();
+        assert_eq!(err.field, Field::Version);
+        assert_eq!(err.offset, ENVELOPE_MARKER.len());
+    }
+
+    #[test]
+    fn test_rejects_tag_without_tagged_layout() {
+        for tag in [0, MovieVersion::V1.as_u8()] {
+            let mut input = ENVELOPE_MARKER.to_vec();
+            input.push(tag);
+            input.extend(back_to_the_future(MovieVersion::V1).bytes);
+            let err = Movie::try_from_slice(&input).unwrap_err();
+            assert_eq!(err.field, Field::Version);
+            assert_eq!(err.kind, DecodeErrorKind::UnknownVersion(tag));
+        }
+    }
+
+    fn tagged_with_extra_field(tag: u8) -> Vec<u8> {
+        let mut extension = "https://www.imdb.com/title/tt0088763/"
+            .try_to_vec()
+            .unwrap();
+        // A field this build does not know yet, e.g. the runtime in minutes.
+        116u16.serialize(&mut extension).unwrap();
+        let mut input = ENVELOPE_MARKER.to_vec();
+        input.push(tag);
+        "Back To The Future".serialize(&mut input).unwrap();
+        Genre::ScienceFiction.serialize(&mut input).unwrap();
+        extension.serialize(&mut input).unwrap();
+        input
+    }
+
+    #[test]
+    fn test_skips_extension_fields_of_newer_versions() {
+        let input = tagged_with_extra_field(MovieVersion::LATEST.as_u8() + 1);
+        let movie = Movie::try_from_slice(&input).unwrap();
+        assert_eq!(movie.version(), MovieVersion::LATEST);
+        assert_eq!(movie, back_to_the_future(MovieVersion::V2).movie);
+    }
+
+    #[test]
+    fn test_rejects_unknown_extension_fields_of_known_versions() {
+        let input = tagged_with_extra_field(MovieVersion::LATEST.as_u8());
+        let err = Movie::try_from_slice(&input).unwrap_err();
+        assert_eq!(err.field, Field::Extension);
+        assert_eq!(err.kind, DecodeErrorKind::ExtensionTooLong(2));
+    }
+
+    #[test]
+    fn test_mixed_versions_in_sequence() {
+        let movies = vec![
+            back_to_the_future(MovieVersion::V1).movie,
+            back_to_the_future(MovieVersion::V2).movie,
+            back_to_the_future(MovieVersion::V1).movie,
+        ];
+        let bytes = movies.try_to_vec().unwrap();
+        assert_eq!(Vec::<Movie>::try_from_slice(&bytes).unwrap(), movies);
+    }
+}
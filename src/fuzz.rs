@@ -0,0 +1,126 @@
+//! Invariants checked by the fuzz target in `fuzz/`, on arbitrary input bytes.
+//!
+//! Every `Movie` decoder must return an error rather than panic, and any record it
+//! accepts must encode back to exactly the bytes it was read from. The one exception
+//! is a record from a newer version, which is read leniently without the extension
+//! fields this build does not know; it must still round trip to the same value.
+//! [`Movie::try_from_slice_strict`] has no exception.
+//!
+//! Allocation is checked by the fuzz target itself, which counts what the decoders
+//! allocate. Only built for tests and with the `fuzzing` feature, which `fuzz/` enables.
+
+use crate::ambiguity::Layout;
+use crate::compat::read_as;
+use crate::{read_tag, Genre, Movie, MovieVersion};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// The largest single allocation checking `input` may make. Decoded strings are
+/// copied out of the input, and re-encoding grows a buffer from Borsh's initial
+/// capacity of 1024 bytes by doubling, so nothing needs more than twice the input.
+pub fn allocation_limit(input: &[u8]) -> usize {
+    2 * input.len() + 1024
+}
+
+pub fn check_decoders(data: &[u8]) {
+    let tag = read_tag(data);
+
+    if let Ok(movie) = Movie::try_from_slice(data) {
+        let tag = tag
+            .as_ref()
+            .expect("read_tag accepts every decodable record");
+        assert_eq!(tag.is_some(), movie.version().is_tagged());
+        check_reencodes(&movie, data);
+    }
+
+    if let Ok(movie) = Movie::try_from_slice_strict(data) {
+        assert_eq!(
+            movie.try_to_vec().unwrap(),
+            data,
+            "strict decoding is canonical"
+        );
+    }
+
+    let mut buf = data;
+    if let Ok(movie) = Movie::deserialize(&mut buf) {
+        check_reencodes(&movie, &data[..data.len() - buf.len()]);
+    }
+
+    for version in MovieVersion::ALL {
+        if let Some(consumed) = version.decode_prefix(data) {
+            assert!(consumed <= data.len());
+            assert_eq!(
+                tag.as_ref().ok(),
+                Some(&version.is_tagged().then(|| version.as_u8()))
+            );
+        }
+        if let Ok(movie) = read_as(version, data) {
+            match (version, &movie) {
+                // The V1 reader predates the envelope and writes the bare struct.
+                (MovieVersion::V1, Movie::V1(original)) => {
+                    assert_eq!(original.try_to_vec().unwrap(), data)
+                }
+                _ => check_reencodes(&movie, data),
+            }
+        }
+    }
+
+    if let Some(&byte) = data.first() {
+        let genre = Genre::try_from_slice(&[byte]).expect("every byte is a genre");
+        assert_eq!(genre.try_to_vec().unwrap(), [byte]);
+    }
+}
+
+fn check_reencodes(movie: &Movie, consumed: &[u8]) {
+    let bytes = movie.try_to_vec().unwrap();
+    let from_newer_version = matches!(
+        read_tag(consumed),
+        Ok(Some(tag)) if tag > MovieVersion::LATEST.as_u8()
+    );
+    if from_newer_version {
+        assert_eq!(&Movie::try_from_slice(&bytes).unwrap(), movie);
+    } else {
+        assert_eq!(bytes, consumed, "{movie:?} does not re-encode to its input");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ambiguity::{candidates, sample_movies};
+
+    /// xorshift64, so the test needs no dependencies and is reproducible.
+    fn pseudo_random_bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed.max(1);
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_check_decoders_on_mutated_encodings() {
+        for input in candidates(&MovieVersion::ALL, &sample_movies()) {
+            check_decoders(&input);
+        }
+    }
+
+    #[test]
+    fn test_check_decoders_on_random_bytes() {
+        for seed in 0..2000 {
+            let input = pseudo_random_bytes(seed, (seed % 64) as usize);
+            check_decoders(&input);
+        }
+    }
+
+    #[test]
+    fn test_hostile_length_prefix() {
+        let mut input = vec![0xff, 0xff, 0xff, 0xff];
+        input.extend_from_slice(b"short");
+        assert!(Movie::try_from_slice(&input).is_err());
+        check_decoders(&input);
+    }
+}
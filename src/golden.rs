@@ -0,0 +1,232 @@
+//! Golden test vectors for every historical `Movie` encoding.
+//!
+//! `golden/v<version>/<genre>.txt` holds one record per `Movie` version and `Genre`:
+//! the version that wrote it, its exact bytes as hex, and the fields it decodes to.
+//! Genres are named by [`Genre::name`], which is pinned, so renaming a variant cannot
+//! move or change them. These files are history and must never change once committed.
+//!
+//! Running the tests with `GOLDEN_RECORD=1` writes the vector of every sample movie
+//! that has none yet, so covering a new version or genre only means adding it to
+//! [`sample_movie`]. Existing vectors are never overwritten.
+
+use crate::{Genre, Movie, MovieV1, MovieV2, MovieVersion};
+use borsh::BorshSerialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Set to record missing golden vectors instead of failing on them.
+pub const RECORD_ENV: &str = "GOLDEN_RECORD";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoldenVector {
+    pub version: MovieVersion,
+    pub bytes: Vec<u8>,
+    pub movie: Movie,
+}
+
+pub fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("golden")
+}
+
+pub fn vector_path(version: MovieVersion, genre: &Genre) -> PathBuf {
+    golden_dir()
+        .join(format!("v{}", version.as_u8()))
+        .join(format!("{}.txt", known_name(genre)))
+}
+
+/// The pinned name of `genre`, which names its files and is written in them.
+fn known_name(genre: &Genre) -> &'static str {
+    genre
+        .name()
+        .unwrap_or_else(|| panic!("no golden vectors for unknown genre {genre:?}"))
+}
+
+impl GoldenVector {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut version = None;
+        let mut bytes = None;
+        let mut title = None;
+        let mut genre = None;
+        let mut imdb_url = None;
+        for line in text.lines() {
+            let (key, value) = line
+                .split_once(": ")
+                .ok_or_else(|| format!("expected `key: value`, got {line:?}"))?;
+            match key {
+                "version" => {
+                    let tag = value
+                        .parse()
+                        .map_err(|_| format!("bad version {value:?}"))?;
+                    version = Some(
+                        MovieVersion::from_u8(tag)
+                            .ok_or_else(|| format!("unknown version {tag}"))?,
+                    );
+                }
+                "hex" => bytes = Some(hex::decode(value).map_err(|err| err.to_string())?),
+                "title" => title = Some(value.to_string()),
+                "genre" => {
+                    genre = Some(
+                        Genre::from_name(value)
+                            .ok_or_else(|| format!("unknown genre {value:?}"))?,
+                    )
+                }
+                "imdb_url" => imdb_url = Some(value.to_string()),
+                _ => return Err(format!("unknown key {key:?}")),
+            }
+        }
+        let version = version.ok_or("missing version")?;
+        let title = title.ok_or("missing title")?;
+        let genre = genre.ok_or("missing genre")?;
+        let movie = match (version, imdb_url) {
+            (MovieVersion::V1, None) => Movie::V1(MovieV1 { title, genre }),
+            (MovieVersion::V2, Some(imdb_url)) => Movie::V2(MovieV2 {
+                title,
+                genre,
+                imdb_url,
+            }),
+            (version, _) => return Err(format!("fields do not match {version:?}")),
+        };
+        Ok(Self {
+            version,
+            bytes: bytes.ok_or("missing hex")?,
+            movie,
+        })
+    }
+
+    pub fn render(&self) -> String {
+        let mut text = format!(
+            "version: {}\nhex: {}\ntitle: {}\ngenre: {}\n",
+            self.version.as_u8(),
+            hex::encode(&self.bytes),
+            self.movie.title(),
+            known_name(self.movie.genre()),
+        );
+        if let Some(imdb_url) = self.movie.imdb_url() {
+            text += &format!("imdb_url: {imdb_url}\n");
+        }
+        text
+    }
+
+    pub fn load(path: &Path) -> Self {
+        let text = fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("reading {}: {}", path.display(), err));
+        Self::parse(&text).unwrap_or_else(|err| panic!("parsing {}: {}", path.display(), err))
+    }
+}
+
+/// Every vector in the corpus, in a stable order.
+pub fn load_corpus() -> Vec<(PathBuf, GoldenVector)> {
+    let mut paths = Vec::new();
+    for version_dir in fs::read_dir(golden_dir()).expect("golden directory exists") {
+        let version_dir = version_dir.unwrap().path();
+        for file in fs::read_dir(&version_dir).unwrap() {
+            paths.push(file.unwrap().path());
+        }
+    }
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let vector = GoldenVector::load(&path);
+            (path, vector)
+        })
+        .collect()
+}
+
+/// The movie recorded for `version` and `genre`.
+pub fn sample_movie(version: MovieVersion, genre: &Genre) -> Movie {
+    let (title, imdb_url) = match genre {
+        Genre::Comedy => ("Groundhog Day", "https://www.imdb.com/title/tt0107048/"),
+        Genre::Drama => ("The Godfather", "https://www.imdb.com/title/tt0068646/"),
+        Genre::Fantasy => ("Spirited Away", "https://www.imdb.com/title/tt0245429/"),
+        Genre::Horror => ("The Shining", "https://www.imdb.com/title/tt0081505/"),
+        Genre::Romance => ("Amélie", "https://www.imdb.com/title/tt0211915/"),
+        Genre::ScienceFiction => (
+            "Back To The Future",
+            "https://www.imdb.com/title/tt0088763/",
+        ),
+        Genre::Unknown(unknown) => panic!("no sample for unknown genre {unknown:?}"),
+    };
+    match version {
+        MovieVersion::V1 => Movie::V1(MovieV1 {
+            title: title.into(),
+            genre: genre.clone(),
+        }),
+        MovieVersion::V2 => Movie::new(title.into(), genre.clone(), imdb_url.into()),
+    }
+}
+
+/// The golden vector of Back To The Future, the record of the original exercise, as
+/// written by `version`.
+pub fn back_to_the_future(version: MovieVersion) -> GoldenVector {
+    GoldenVector::load(&vector_path(version, &Genre::ScienceFiction))
+}
+
+/// Checks that `movie` serializes to the bytes of its golden vector at `path`. If the
+/// vector does not exist and [`RECORD_ENV`] is set, records it instead.
+pub fn check_or_record(path: &Path, movie: &Movie) {
+    let vector = GoldenVector {
+        version: movie.version(),
+        bytes: movie.try_to_vec().unwrap(),
+        movie: movie.clone(),
+    };
+    if !path.exists() && env::var_os(RECORD_ENV).is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vector.render())
+            .unwrap_or_else(|err| panic!("recording {}: {}", path.display(), err));
+        return;
+    }
+    if !path.exists() {
+        panic!(
+            "missing golden vector {}, run with {}=1 to record it",
+            path.display(),
+            RECORD_ENV
+        );
+    }
+    assert_eq!(
+        GoldenVector::load(path),
+        vector,
+        "{} no longer matches; committed vectors must not change",
+        path.display()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_golden_vectors() {
+        let corpus = load_corpus();
+        assert!(!corpus.is_empty());
+        for (path, vector) in corpus {
+            let name = path.display();
+            let decoded = Movie::try_from_slice(&vector.bytes)
+                .unwrap_or_else(|err| panic!("decoding {name}: {err}"));
+            assert_eq!(decoded, vector.movie, "{name}");
+            assert_eq!(decoded.version(), vector.version, "{name}");
+            assert_eq!(decoded.try_to_vec().unwrap(), vector.bytes, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_golden_samples() {
+        for version in MovieVersion::ALL {
+            for genre in Genre::ALL {
+                check_or_record(
+                    &vector_path(version, &genre),
+                    &sample_movie(version, &genre),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_round_trip() {
+        for (path, vector) in load_corpus() {
+            let text = fs::read_to_string(&path).unwrap();
+            assert_eq!(vector.render(), text, "{}", path.display());
+        }
+    }
+}
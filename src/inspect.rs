@@ -0,0 +1,156 @@
+//! Annotated dumps of encoded `Movie` records, labelling every byte with the field it
+//! belongs to:
+//!
+//! ```text
+//!  0..4   12000000                          title length 18
+//!  4..22  4261636b20546f205468652046757475  title "Back To The Future"
+//!         7265
+//! 22..23  05                                genre Genre::ScienceFiction
+//! ```
+//!
+//! Bytes that cannot be decoded are shown as well, followed by the error that stopped
+//! the decoder.
+
+use crate::decode::{decode_movie, Mode, Reader};
+use crate::{DecodeError, Movie};
+use std::fmt;
+use std::ops::Range;
+
+/// Bytes shown per line of a dump.
+const BYTES_PER_LINE: usize = 16;
+
+/// A labelled byte range of an encoded record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub range: Range<usize>,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dump {
+    pub bytes: Vec<u8>,
+    pub segments: Vec<Segment>,
+    /// What the bytes decode to, or why they do not.
+    pub result: Result<Movie, DecodeError>,
+}
+
+/// Decodes `bytes` as a single `Movie`, labelling each field on the way.
+pub fn inspect(bytes: &[u8]) -> Dump {
+    let mut reader = Reader::recording(bytes);
+    let result =
+        decode_movie(&mut reader, Mode::Lenient).and_then(|movie| reader.finish().map(|()| movie));
+    let mut segments = reader.into_segments();
+    let end = segments.last().map_or(0, |segment| segment.range.end);
+    if end < bytes.len() {
+        let label = match &result {
+            Ok(_) => "trailing bytes",
+            Err(_) => "not decoded",
+        };
+        segments.push(Segment {
+            range: end..bytes.len(),
+            label: label.into(),
+        });
+    }
+    Dump {
+        bytes: bytes.to_vec(),
+        segments,
+        result,
+    }
+}
+
+impl fmt::Display for Dump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let offset_width = self.bytes.len().to_string().len();
+        for segment in &self.segments {
+            let range = format!(
+                "{:>width$}..{:<width$}",
+                segment.range.start,
+                segment.range.end,
+                width = offset_width
+            );
+            let bytes = &self.bytes[segment.range.clone()];
+            let mut lines = bytes.chunks(BYTES_PER_LINE).map(to_hex);
+            let first = lines.next().unwrap_or_default();
+            writeln!(
+                f,
+                "{}  {:<hex_width$}  {}",
+                range,
+                first,
+                segment.label,
+                hex_width = 2 * BYTES_PER_LINE
+            )?;
+            for line in lines {
+                writeln!(f, "{:width$}  {}", "", line, width = range.len())?;
+            }
+        }
+        if let Err(err) = &self.result {
+            writeln!(f, "error: {err}")?;
+        }
+        Ok(())
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::golden::back_to_the_future;
+    use crate::{Genre, MovieVersion};
+    use borsh::BorshSerialize;
+
+    fn labels(dump: &Dump) -> Vec<(Range<usize>, &str)> {
+        dump.segments
+            .iter()
+            .map(|segment| (segment.range.clone(), segment.label.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_inspect_legacy_record() {
+        let dump = inspect(&back_to_the_future(MovieVersion::V1).bytes);
+        assert_eq!(
+            dump.to_string(),
+            r#" 0..4   12000000                          title length 18
+ 4..22  4261636b20546f205468652046757475  title "Back To The Future"
+        7265
+22..23  05                                genre Genre::ScienceFiction
+"#
+        );
+    }
+
+    #[test]
+    fn test_inspect_tagged_record() {
+        let movie = Movie::new("Up".into(), Genre::Comedy, "u".into());
+        let dump = inspect(&movie.try_to_vec().unwrap());
+        assert_eq!(dump.result, Ok(movie));
+        assert_eq!(
+            labels(&dump),
+            vec![
+                (0..5, "envelope marker"),
+                (5..6, "version tag 2 = V2"),
+                (6..10, "title length 2"),
+                (10..12, "title \"Up\""),
+                (12..13, "genre Genre::Comedy"),
+                (13..17, "extension length 5"),
+                (17..21, "imdb_url length 1"),
+                (21..22, "imdb_url \"u\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inspect_shows_undecoded_bytes() {
+        let legacy = back_to_the_future(MovieVersion::V1).bytes;
+        let dump = inspect(&legacy[..10]);
+        assert_eq!(
+            labels(&dump),
+            vec![(0..4, "title length 18"), (4..10, "not decoded")]
+        );
+        assert!(dump
+            .to_string()
+            .ends_with("error: Movie title at byte 4: needs 18 bytes but only 6 are left\n"));
+    }
+}
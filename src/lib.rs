@@ -1,12 +1,69 @@
-use borsh::{BorshDeserialize, BorshSerialize};
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+use std::fmt;
+use std::io;
 
-#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
-pub struct Movie {
+pub mod ambiguity;
+mod appended;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod compat;
+mod decode;
+#[cfg(feature = "cli")]
+pub mod dump;
+mod envelope;
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod fuzz;
+#[cfg(test)]
+mod golden;
+pub mod inspect;
+#[cfg(feature = "cli")]
+pub mod migrate;
+#[cfg(test)]
+mod proptests;
+mod schema;
+pub mod schema_diff;
+mod serde_impl;
+mod strict;
+mod upgrade;
+#[cfg(feature = "cli")]
+pub mod verify;
+
+pub use appended::Appended;
+pub use borsh_compat_derive::BorshCompat;
+pub use decode::{read_tag, DecodeError, DecodeErrorKind, Field};
+pub use envelope::{MovieVersion, ENVELOPE_MARKER};
+pub use schema::render_schema;
+pub use upgrade::imdb_search_url;
+
+/// A movie in any of the layouts that have ever been written on chain.
+///
+/// New movies are always created with [`Movie::new`], which requires an IMDB URL.
+/// The `V1` variant only exists so that historical records keep decoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Movie {
+    V1(MovieV1),
+    V2(MovieV2),
+}
+
+/// The original layout of `Movie`, before `imdb_url` was added.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct MovieV1 {
+    pub title: String,
+    pub genre: Genre,
+}
+
+/// The current fields of `Movie`. On the wire these are split into core fields and
+/// an extension block, see [`Movie`]'s `BorshSerialize` implementation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovieV2 {
     pub title: String,
     pub genre: Genre,
+    pub imdb_url: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+/// The wire byte of every genre is pinned in [`Genre::as_u8`] and does not depend on
+/// the order in which variants are declared.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Genre {
     Comedy,
     Drama,
@@ -14,6 +71,129 @@ pub enum Genre {
     Horror,
     Romance,
     ScienceFiction,
+    /// A genre added after this build, kept as its wire byte so it re-encodes unchanged.
+    Unknown(UnknownGenre),
+}
+
+/// The wire byte of a genre this build does not know. Only [`Genre::from_u8`] creates
+/// one, so it never holds the byte of a known genre.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct UnknownGenre(u8);
+
+impl UnknownGenre {
+    pub fn as_u8(self) -> u8 {
+        self.0
+    }
+}
+
+impl fmt::Debug for UnknownGenre {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Genre {
+    /// Every genre this build knows.
+    pub const ALL: [Self; 6] = [
+        Self::Comedy,
+        Self::Drama,
+        Self::Fantasy,
+        Self::Horror,
+        Self::Romance,
+        Self::ScienceFiction,
+    ];
+
+    pub fn from_u8(byte: u8) -> Self {
+        match byte {
+            0 => Self::Comedy,
+            1 => Self::Drama,
+            2 => Self::Fantasy,
+            3 => Self::Horror,
+            4 => Self::Romance,
+            5 => Self::ScienceFiction,
+            byte => Self::Unknown(UnknownGenre(byte)),
+        }
+    }
+
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            Self::Comedy => 0,
+            Self::Drama => 1,
+            Self::Fantasy => 2,
+            Self::Horror => 3,
+            Self::Romance => 4,
+            Self::ScienceFiction => 5,
+            Self::Unknown(unknown) => unknown.as_u8(),
+        }
+    }
+
+    /// The genre's name in text formats, pinned like its wire byte. `None` for
+    /// [`Genre::Unknown`].
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            Self::Comedy => Some("comedy"),
+            Self::Drama => Some("drama"),
+            Self::Fantasy => Some("fantasy"),
+            Self::Horror => Some("horror"),
+            Self::Romance => Some("romance"),
+            Self::ScienceFiction => Some("science_fiction"),
+            Self::Unknown(_) => None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|genre| genre.name() == Some(name))
+    }
+
+    pub fn is_known(&self) -> bool {
+        !matches!(self, Self::Unknown(_))
+    }
+}
+
+impl BorshSerialize for Genre {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.as_u8().serialize(writer)
+    }
+}
+
+impl BorshDeserialize for Genre {
+    fn deserialize(buf: &mut &[u8]) -> io::Result<Self> {
+        u8::deserialize(buf).map(Self::from_u8)
+    }
+}
+
+impl Movie {
+    pub fn new(title: String, genre: Genre, imdb_url: String) -> Self {
+        Self::V2(MovieV2 {
+            title,
+            genre,
+            imdb_url,
+        })
+    }
+
+    pub fn title(&self) -> &str {
+        match self {
+            Self::V1(movie) => &movie.title,
+            Self::V2(movie) => &movie.title,
+        }
+    }
+
+    pub fn genre(&self) -> &Genre {
+        match self {
+            Self::V1(movie) => &movie.genre,
+            Self::V2(movie) => &movie.genre,
+        }
+    }
+
+    /// The IMDB URL, which is absent only for records written before it was introduced.
+    pub fn imdb_url(&self) -> Option<&str> {
+        match self {
+            Self::V1(_) => None,
+            Self::V2(movie) => Some(&movie.imdb_url),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -21,13 +201,80 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_deserialize_movie() {
-        let input = hex::decode("120000004261636b20546f205468652046757475726505").unwrap();
+    fn test_movie_v2_round_trip() {
+        let movie = Movie::new(
+            "Back To The Future".into(),
+            Genre::ScienceFiction,
+            "https://www.imdb.com/title/tt0088763/".into(),
+        );
+        let serialized_movie = movie.try_to_vec().unwrap();
+        assert_eq!(serialized_movie[..5], ENVELOPE_MARKER);
+        assert_eq!(Movie::try_from_slice(&serialized_movie).unwrap(), movie);
+    }
+
+    #[test]
+    fn test_genre_unknown_round_trip() {
+        for byte in 0..=u8::MAX {
+            let genre = Genre::try_from_slice(&[byte]).unwrap();
+            assert_eq!(genre.is_known(), (byte as usize) < Genre::ALL.len());
+            assert_eq!(genre.try_to_vec().unwrap(), [byte]);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_movie_with_unknown_genre() {
+        let input = hex::decode("120000004261636b20546f20546865204675747572652a").unwrap();
         let deserialized_movie = Movie::try_from_slice(&input).unwrap();
-        let expected_movie = Movie {
-            title: "Back To The Future".into(),
-            genre: Genre::ScienceFiction,
-        };
-        assert_eq!(deserialized_movie, expected_movie,);
+        assert_eq!(deserialized_movie.genre(), &Genre::from_u8(0x2a));
+        assert_eq!(deserialized_movie.try_to_vec().unwrap(), input);
+    }
+
+    /// The released wire byte of each genre. Entries may be appended, but never
+    /// changed or removed: every one of these bytes is already on chain.
+    fn pinned_discriminant(genre: &Genre) -> Option<u8> {
+        // No wildcard arm, so adding or removing a variant fails to compile here.
+        match genre {
+            Genre::Comedy => Some(0),
+            Genre::Drama => Some(1),
+            Genre::Fantasy => Some(2),
+            Genre::Horror => Some(3),
+            Genre::Romance => Some(4),
+            Genre::ScienceFiction => Some(5),
+            Genre::Unknown(_) => None,
+        }
+    }
+
+    #[test]
+    fn test_genre_discriminants_are_pinned() {
+        for genre in Genre::ALL {
+            let byte = pinned_discriminant(&genre).unwrap();
+            assert_eq!(genre.as_u8(), byte, "{genre:?}");
+            assert_eq!(genre.try_to_vec().unwrap(), [byte], "{genre:?}");
+            assert_eq!(Genre::from_u8(byte), genre);
+        }
+    }
+
+    #[test]
+    fn test_genre_discriminants_are_unique() {
+        let mut bytes: Vec<_> = Genre::ALL.iter().map(Genre::as_u8).collect();
+        bytes.sort_unstable();
+        bytes.dedup();
+        assert_eq!(bytes.len(), Genre::ALL.len());
+        for byte in 0..=u8::MAX {
+            let genre = Genre::from_u8(byte);
+            assert_eq!(genre.as_u8(), byte);
+            assert_eq!(genre.is_known(), bytes.contains(&byte));
+        }
+    }
+
+    #[test]
+    fn test_genre_names_round_trip() {
+        for genre in Genre::ALL {
+            let name = genre.name().unwrap();
+            assert_eq!(Genre::from_name(name), Some(genre));
+        }
+        assert_eq!(Genre::ScienceFiction.name(), Some("science_fiction"));
+        assert_eq!(Genre::from_u8(42).name(), None);
+        assert_eq!(Genre::from_name("ScienceFiction"), None);
     }
 }
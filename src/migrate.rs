@@ -0,0 +1,210 @@
+//! Rewrites a dump of records in any version into the latest encoding.
+//!
+//! Only for data that may be rewritten, such as off-chain indexes and archives: the
+//! chain itself keeps every record in the version it was written in. Fields that did
+//! not exist yet are filled as by [`Movie::into_latest`].
+
+use crate::dump::{write_record, Format, Records};
+use crate::{read_tag, Movie, MovieVersion};
+use borsh::BorshSerialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+
+/// Passes bytes through while hashing them with SHA-256.
+struct Hashing<T> {
+    inner: T,
+    hasher: Sha256,
+}
+
+impl<T> Hashing<T> {
+    fn new(inner: T) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    fn finish(self) -> [u8; 32] {
+        self.hasher.finalize().into()
+    }
+}
+
+impl<R: Read> Read for Hashing<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for Hashing<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        // The buffer is only refilled once consumed, so this is what `fill_buf` returned.
+        if let Ok(buf) = self.inner.fill_buf() {
+            self.hasher.update(&buf[..amt]);
+        }
+        self.inner.consume(amt);
+    }
+}
+
+impl<W: Write> Write for Hashing<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    pub records: usize,
+    /// Records read, by the version they were written in.
+    pub versions: BTreeMap<MovieVersion, usize>,
+    /// Records whose bytes changed.
+    pub rewritten: usize,
+    pub input_sha256: [u8; 32],
+    pub output_sha256: [u8; 32],
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "records: {}", self.records)?;
+        for version in MovieVersion::ALL {
+            let count = self.versions.get(&version).copied().unwrap_or_default();
+            writeln!(f, "{version:?}: {count}")?;
+        }
+        writeln!(f, "rewritten: {}", self.rewritten)?;
+        writeln!(f, "input sha256: {}", hex::encode(self.input_sha256))?;
+        writeln!(f, "output sha256: {}", hex::encode(self.output_sha256))
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads every record from `input` and writes it to `output` in the latest version,
+/// both in `format`. Pass [`io::sink`] as `output` for a dry run.
+///
+/// Fails on the first record that does not decode, or that was written by a newer
+/// version whose fields would be lost.
+pub fn migrate<R: BufRead, W: Write>(input: R, output: W, format: Format) -> io::Result<Summary> {
+    let mut input = Hashing::new(input);
+    let mut output = Hashing::new(output);
+    let mut versions = BTreeMap::new();
+    let mut records = 0;
+    let mut rewritten = 0;
+    for record in Records::new(&mut input, format) {
+        let record = record?;
+        let location = record.location;
+        let bytes = record
+            .bytes
+            .map_err(|err| invalid(format!("{location}: {err}")))?;
+        if let Ok(Some(tag)) = read_tag(&bytes) {
+            if tag > MovieVersion::LATEST.as_u8() {
+                return Err(invalid(format!(
+                    "{location}: written by newer version {tag}"
+                )));
+            }
+        }
+        let movie =
+            Movie::try_from_slice(&bytes).map_err(|err| invalid(format!("{location}: {err}")))?;
+        *versions.entry(movie.version()).or_default() += 1;
+        let latest = Movie::V2(movie.into_latest()).try_to_vec()?;
+        if latest != bytes {
+            rewritten += 1;
+        }
+        write_record(&mut output, format, &latest)?;
+        records += 1;
+    }
+    output.flush()?;
+    Ok(Summary {
+        records,
+        versions,
+        rewritten,
+        input_sha256: input.finish(),
+        output_sha256: output.finish(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Encoding;
+    use crate::golden::back_to_the_future;
+    use crate::verify::verify;
+    use crate::{Genre, ENVELOPE_MARKER};
+
+    fn sha256(bytes: &[u8]) -> [u8; 32] {
+        Sha256::digest(bytes).into()
+    }
+
+    fn mixed_dump() -> Vec<u8> {
+        let tagged = Movie::new("Up".into(), Genre::Comedy, "u".into())
+            .try_to_vec()
+            .unwrap();
+        let mut dump = Vec::new();
+        for record in [back_to_the_future(MovieVersion::V1).bytes, tagged] {
+            write_record(&mut dump, Format::Framed, &record).unwrap();
+        }
+        dump
+    }
+
+    #[test]
+    fn test_migrate_to_latest() {
+        let input = mixed_dump();
+        let mut output = Vec::new();
+        let summary = migrate(input.as_slice(), &mut output, Format::Framed).unwrap();
+        assert_eq!(summary.records, 2);
+        assert_eq!(summary.versions[&MovieVersion::V1], 1);
+        assert_eq!(summary.versions[&MovieVersion::V2], 1);
+        assert_eq!(summary.rewritten, 1);
+        assert_eq!(summary.input_sha256, sha256(&input));
+        assert_eq!(summary.output_sha256, sha256(&output));
+
+        let report = verify(Records::new(output.as_slice(), Format::Framed)).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.versions.get(&MovieVersion::V1), None);
+        assert_eq!(report.versions[&MovieVersion::V2], 2);
+
+        // Migrating again changes nothing.
+        let again = migrate(output.as_slice(), io::sink(), Format::Framed).unwrap();
+        assert_eq!(again.rewritten, 0);
+        assert_eq!(again.input_sha256, again.output_sha256);
+    }
+
+    #[test]
+    fn test_dry_run_checksums_output() {
+        let legacy = hex::encode(back_to_the_future(MovieVersion::V1).bytes);
+        let input = format!("{legacy}\n\n{legacy}\n");
+        let format = Format::Lines(Encoding::Hex);
+        let mut output = Vec::new();
+        let written = migrate(input.as_bytes(), &mut output, format).unwrap();
+        let dry_run = migrate(input.as_bytes(), io::sink(), format).unwrap();
+        assert_eq!(dry_run, written);
+        assert_eq!(dry_run.input_sha256, sha256(input.as_bytes()));
+        assert_eq!(dry_run.output_sha256, sha256(&output));
+    }
+
+    #[test]
+    fn test_migrate_refuses_newer_versions() {
+        let mut newer = Movie::new("Up".into(), Genre::Comedy, "u".into())
+            .try_to_vec()
+            .unwrap();
+        newer[ENVELOPE_MARKER.len()] = MovieVersion::LATEST.as_u8() + 1;
+        let legacy = hex::encode(back_to_the_future(MovieVersion::V1).bytes);
+        let input = format!("{}\n{}\n", legacy, hex::encode(newer));
+        let err = migrate(input.as_bytes(), io::sink(), Format::Lines(Encoding::Hex)).unwrap_err();
+        assert_eq!(err.to_string(), "line 2: written by newer version 3");
+    }
+}
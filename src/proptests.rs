@@ -0,0 +1,86 @@
+//! Property tests: every `Movie` and `Genre` survives a Borsh and a JSON round trip.
+
+use crate::{Genre, Movie, MovieV1, MovieV2, MovieVersion};
+use borsh::{BorshDeserialize, BorshSerialize};
+use proptest::prelude::*;
+
+pub fn genre() -> impl Strategy<Value = Genre> {
+    prop_oneof![
+        3 => proptest::sample::select(Genre::ALL.to_vec()),
+        1 => any::<u8>().prop_map(Genre::from_u8),
+    ]
+}
+
+/// Empty, ASCII and arbitrary unicode strings, including multi-byte characters.
+pub fn text() -> impl Strategy<Value = String> {
+    prop_oneof![Just(String::new()), "[ -~]{0,40}", any::<String>()]
+}
+
+pub fn movie_v1() -> impl Strategy<Value = MovieV1> {
+    (text(), genre()).prop_map(|(title, genre)| MovieV1 { title, genre })
+}
+
+pub fn movie_v2() -> impl Strategy<Value = MovieV2> {
+    (text(), genre(), text()).prop_map(|(title, genre, imdb_url)| MovieV2 {
+        title,
+        genre,
+        imdb_url,
+    })
+}
+
+pub fn movie() -> impl Strategy<Value = Movie> {
+    prop_oneof![
+        movie_v1().prop_map(Movie::V1),
+        movie_v2().prop_map(Movie::V2)
+    ]
+}
+
+/// A movie written with `version`.
+pub fn movie_of(version: MovieVersion) -> BoxedStrategy<Movie> {
+    match version {
+        MovieVersion::V1 => movie_v1().prop_map(Movie::V1).boxed(),
+        MovieVersion::V2 => movie_v2().prop_map(Movie::V2).boxed(),
+    }
+}
+
+proptest! {
+    #[test]
+    fn test_genre_round_trip(genre in genre()) {
+        let bytes = genre.try_to_vec().unwrap();
+        prop_assert_eq!(Genre::try_from_slice(&bytes).unwrap(), genre);
+    }
+
+    #[test]
+    fn test_movie_round_trip_every_version(
+        movies in MovieVersion::ALL.map(movie_of),
+    ) {
+        for (version, movie) in MovieVersion::ALL.into_iter().zip(movies) {
+            let bytes = movie.try_to_vec().unwrap();
+            let decoded = Movie::try_from_slice(&bytes).unwrap();
+            prop_assert_eq!(decoded.version(), version);
+            prop_assert_eq!(decoded, movie);
+        }
+    }
+
+    #[test]
+    fn test_movie_sequence_round_trip(movies in proptest::collection::vec(movie(), 0..8)) {
+        let bytes = movies.try_to_vec().unwrap();
+        prop_assert_eq!(Vec::<Movie>::try_from_slice(&bytes).unwrap(), movies);
+    }
+
+    #[test]
+    fn test_upgrade_keeps_core_fields(movie in movie()) {
+        let latest = movie.clone().into_latest();
+        prop_assert_eq!(latest.title.as_str(), movie.title());
+        prop_assert_eq!(&latest.genre, movie.genre());
+        let upgraded = Movie::V2(latest);
+        let bytes = upgraded.try_to_vec().unwrap();
+        prop_assert_eq!(Movie::try_from_slice(&bytes).unwrap(), upgraded);
+    }
+
+    #[test]
+    fn test_movie_json_round_trip(movie in movie()) {
+        let json = serde_json::to_string(&movie).unwrap();
+        prop_assert_eq!(serde_json::from_str::<Movie>(&json).unwrap(), movie);
+    }
+}
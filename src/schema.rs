@@ -0,0 +1,170 @@
+use crate::{Genre, MovieV1, MovieV2, MovieVersion};
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+use borsh::BorshSchema;
+use std::collections::{BTreeMap, HashMap};
+
+// `Unknown` is not part of the schema: it only stands in for genres that a newer
+// schema declares.
+impl BorshSchema for Genre {
+    fn add_definitions_recursively(definitions: &mut HashMap<Declaration, Definition>) {
+        let mut variants: Vec<_> = Self::ALL
+            .iter()
+            .map(|genre| (genre.as_u8(), format!("{genre:?}")))
+            .collect();
+        variants.sort();
+        let definition = Definition::Enum {
+            variants: variants
+                .into_iter()
+                .map(|(_, name)| (name, <()>::declaration()))
+                .collect(),
+        };
+        Self::add_definition(Self::declaration(), definition, definitions);
+    }
+
+    fn declaration() -> Declaration {
+        "Genre".into()
+    }
+}
+
+/// The tagged layout written for V2 records, spelled out field by field so that its
+/// schema can be derived.
+mod layout {
+    use crate::{Genre, MovieVersion, ENVELOPE_MARKER};
+    use borsh::{BorshSchema, BorshSerialize};
+
+    #[derive(BorshSerialize, BorshSchema)]
+    pub(super) struct MovieV2 {
+        marker: [u8; ENVELOPE_MARKER.len()],
+        version: u8,
+        title: String,
+        genre: Genre,
+        extension_len: u32,
+        imdb_url: String,
+    }
+
+    impl From<&crate::MovieV2> for MovieV2 {
+        fn from(movie: &crate::MovieV2) -> Self {
+            let extension_len = 4 + movie.imdb_url.len();
+            Self {
+                marker: ENVELOPE_MARKER,
+                version: MovieVersion::V2.as_u8(),
+                title: movie.title.clone(),
+                genre: movie.genre.clone(),
+                extension_len: extension_len.try_into().expect("extension fits in a u32"),
+                imdb_url: movie.imdb_url.clone(),
+            }
+        }
+    }
+}
+
+impl BorshSchema for MovieV2 {
+    fn add_definitions_recursively(definitions: &mut HashMap<Declaration, Definition>) {
+        layout::MovieV2::add_definitions_recursively(definitions);
+    }
+
+    fn declaration() -> Declaration {
+        layout::MovieV2::declaration()
+    }
+}
+
+impl MovieVersion {
+    /// The schema of the layout records of this version are written with.
+    pub fn schema(self) -> BorshSchemaContainer {
+        match self {
+            Self::V1 => MovieV1::schema_container(),
+            Self::V2 => MovieV2::schema_container(),
+        }
+    }
+}
+
+/// Renders a schema as stable, diffable text, with definitions sorted by name.
+pub fn render_schema(schema: &BorshSchemaContainer) -> String {
+    let mut out = format!("declaration: {}\n", schema.declaration);
+    let definitions: BTreeMap<_, _> = schema.definitions.iter().collect();
+    for (declaration, definition) in definitions {
+        match definition {
+            Definition::Array { length, elements } => {
+                out += &format!("{declaration} = array [{elements}; {length}]\n");
+            }
+            Definition::Sequence { elements } => {
+                out += &format!("{declaration} = sequence [{elements}]\n");
+            }
+            Definition::Tuple { elements } => {
+                out += &format!("{} = tuple ({})\n", declaration, elements.join(", "));
+            }
+            Definition::Enum { variants } => {
+                out += &format!("{declaration} = enum {{\n");
+                for (discriminant, (name, variant)) in variants.iter().enumerate() {
+                    out += &format!("    {discriminant} {name}: {variant}\n");
+                }
+                out += "}\n";
+            }
+            Definition::Struct { fields } => {
+                out += &format!("{declaration} = struct {{\n");
+                match fields {
+                    Fields::NamedFields(fields) => {
+                        for (name, field) in fields {
+                            out += &format!("    {name}: {field}\n");
+                        }
+                    }
+                    Fields::UnnamedFields(fields) => {
+                        for field in fields {
+                            out += &format!("    {field}\n");
+                        }
+                    }
+                    Fields::Empty => {}
+                }
+                out += "}\n";
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::golden::sample_movie;
+    use crate::Movie;
+    use borsh::BorshSerialize;
+
+    /// The schema of every released version. These files must never change; a new
+    /// version gets a new snapshot.
+    const SNAPSHOTS: [(MovieVersion, &str); 2] = [
+        (MovieVersion::V1, include_str!("../schemas/movie_v1.txt")),
+        (MovieVersion::V2, include_str!("../schemas/movie_v2.txt")),
+    ];
+
+    #[test]
+    fn test_schemas_match_snapshots() {
+        for (version, snapshot) in SNAPSHOTS {
+            assert_eq!(
+                render_schema(&version.schema()),
+                snapshot,
+                "schema of {version:?} changed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_v2_layout_matches_encoding() {
+        for genre in Genre::ALL {
+            let movie = sample_movie(MovieVersion::V2, &genre);
+            let layout = match &movie {
+                Movie::V2(fields) => layout::MovieV2::from(fields),
+                Movie::V1(_) => unreachable!(),
+            };
+            assert_eq!(
+                layout.try_to_vec().unwrap(),
+                movie.try_to_vec().unwrap(),
+                "{genre:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_every_version_has_a_snapshot() {
+        let snapshotted: Vec<_> = SNAPSHOTS.iter().map(|(version, _)| *version).collect();
+        assert_eq!(snapshotted, MovieVersion::ALL);
+    }
+}
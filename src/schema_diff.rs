@@ -0,0 +1,536 @@
+//! Structural comparison of two Borsh schemas, e.g. an old and a new `Movie` layout.
+//!
+//! Borsh has no field names or type information on the wire, only bytes in
+//! declaration order, so changes are judged by what they do to existing encodings:
+//!
+//! * renaming a field or variant changes nothing on the wire and is compatible;
+//! * appending an enum variant is compatible, since every old encoding still decodes
+//!   to the same value;
+//! * appending a struct field makes every old encoding too short, so old records need
+//!   a migration path such as a new version or an [`Appended`](crate::Appended) field;
+//! * changing a `String` to a `Vec<u8>` is compatible, since both are encoded the same
+//!   way and every string is a valid byte vector;
+//! * removing, inserting or reordering members, or changing any other type, changes
+//!   the meaning of existing bytes and is breaking. That includes a `Vec<u8>` becoming
+//!   a `String`, which old records that are not UTF-8 fail to decode as.
+
+use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Compatibility {
+    Compatible,
+    NeedsMigration,
+    Breaking,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeKind {
+    FieldAppended,
+    FieldInserted,
+    FieldRemoved,
+    FieldReordered {
+        old_index: usize,
+        new_index: usize,
+    },
+    FieldRenamed {
+        old_name: String,
+    },
+    VariantAppended,
+    VariantInserted,
+    VariantRemoved,
+    VariantReordered {
+        old_index: usize,
+        new_index: usize,
+    },
+    VariantRenamed {
+        old_name: String,
+    },
+    TypeChanged {
+        old: Declaration,
+        new: Declaration,
+    },
+    /// A type changed to one that decodes every encoding of the old type to the same
+    /// bytes, such as `string` to `Vec<u8>`.
+    TypeRelaxed {
+        old: Declaration,
+        new: Declaration,
+    },
+    LengthChanged {
+        old: u32,
+        new: u32,
+    },
+}
+
+/// One difference between two schemas. `path` names the affected member, starting
+/// from the declaration of the old root type, e.g. `Movie.imdb_url`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaChange {
+    pub path: String,
+    pub kind: ChangeKind,
+}
+
+impl SchemaChange {
+    pub fn compatibility(&self) -> Compatibility {
+        match self.kind {
+            ChangeKind::FieldRenamed { .. }
+            | ChangeKind::VariantAppended
+            | ChangeKind::VariantRenamed { .. }
+            | ChangeKind::TypeRelaxed { .. } => Compatibility::Compatible,
+            ChangeKind::FieldAppended => Compatibility::NeedsMigration,
+            ChangeKind::FieldInserted
+            | ChangeKind::FieldRemoved
+            | ChangeKind::FieldReordered { .. }
+            | ChangeKind::VariantInserted
+            | ChangeKind::VariantRemoved
+            | ChangeKind::VariantReordered { .. }
+            | ChangeKind::TypeChanged { .. }
+            | ChangeKind::LengthChanged { .. } => Compatibility::Breaking,
+        }
+    }
+}
+
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}: ", self.compatibility(), self.path)?;
+        match &self.kind {
+            ChangeKind::FieldAppended => write!(f, "field appended"),
+            ChangeKind::FieldInserted => write!(f, "field inserted before existing fields"),
+            ChangeKind::FieldRemoved => write!(f, "field removed"),
+            ChangeKind::FieldReordered {
+                old_index,
+                new_index,
+            } => write!(f, "field moved from {old_index} to {new_index}"),
+            ChangeKind::FieldRenamed { old_name } => write!(f, "field renamed from {old_name}"),
+            ChangeKind::VariantAppended => write!(f, "variant appended"),
+            ChangeKind::VariantInserted => write!(f, "variant inserted before existing variants"),
+            ChangeKind::VariantRemoved => write!(f, "variant removed"),
+            ChangeKind::VariantReordered {
+                old_index,
+                new_index,
+            } => write!(f, "variant moved from {old_index} to {new_index}"),
+            ChangeKind::VariantRenamed { old_name } => {
+                write!(f, "variant renamed from {old_name}")
+            }
+            ChangeKind::TypeChanged { old, new } => {
+                write!(f, "type changed from {old} to {new}")
+            }
+            ChangeKind::TypeRelaxed { old, new } => {
+                write!(f, "type relaxed from {old} to {new}")
+            }
+            ChangeKind::LengthChanged { old, new } => {
+                write!(f, "array length changed from {old} to {new}")
+            }
+        }
+    }
+}
+
+/// Lists every difference between `old` and `new`, comparing the root types and
+/// everything reachable from them by position.
+pub fn diff_schemas(old: &BorshSchemaContainer, new: &BorshSchemaContainer) -> Vec<SchemaChange> {
+    let mut differ = Differ {
+        old,
+        new,
+        visited: HashSet::new(),
+        changes: Vec::new(),
+    };
+    differ.diff_types(&old.declaration, &new.declaration, &old.declaration);
+    differ.changes
+}
+
+/// The most severe compatibility label among `changes`, or `Compatible` if empty.
+pub fn overall_compatibility(changes: &[SchemaChange]) -> Compatibility {
+    changes
+        .iter()
+        .map(SchemaChange::compatibility)
+        .max()
+        .unwrap_or(Compatibility::Compatible)
+}
+
+/// Whether `declaration` is a `Vec<u8>`, which is encoded exactly like a `String`.
+fn is_byte_vector(schema: &BorshSchemaContainer, declaration: &Declaration) -> bool {
+    matches!(
+        schema.definitions.get(declaration),
+        Some(Definition::Sequence { elements }) if elements == "u8"
+    )
+}
+
+#[derive(Clone, Copy)]
+enum Member {
+    Field,
+    Variant,
+}
+
+struct Differ<'a> {
+    old: &'a BorshSchemaContainer,
+    new: &'a BorshSchemaContainer,
+    visited: HashSet<(Declaration, Declaration)>,
+    changes: Vec<SchemaChange>,
+}
+
+impl Differ<'_> {
+    fn push(&mut self, path: &str, kind: ChangeKind) {
+        self.changes.push(SchemaChange {
+            path: path.into(),
+            kind,
+        });
+    }
+
+    fn diff_types(&mut self, old: &Declaration, new: &Declaration, path: &str) {
+        if !self.visited.insert((old.clone(), new.clone())) {
+            return;
+        }
+        let type_changed = ChangeKind::TypeChanged {
+            old: old.clone(),
+            new: new.clone(),
+        };
+        if old == "string" && is_byte_vector(self.new, new) {
+            let relaxed = ChangeKind::TypeRelaxed {
+                old: old.clone(),
+                new: new.clone(),
+            };
+            return self.push(path, relaxed);
+        }
+        let (old_definition, new_definition) =
+            match (self.old.definitions.get(old), self.new.definitions.get(new)) {
+                (None, None) if old == new => return,
+                (Some(old_definition), Some(new_definition)) => (old_definition, new_definition),
+                _ => return self.push(path, type_changed),
+            };
+        match (old_definition, new_definition) {
+            (
+                Definition::Array {
+                    length: old_length,
+                    elements: old_elements,
+                },
+                Definition::Array {
+                    length: new_length,
+                    elements: new_elements,
+                },
+            ) => {
+                if old_length != new_length {
+                    self.push(
+                        path,
+                        ChangeKind::LengthChanged {
+                            old: *old_length,
+                            new: *new_length,
+                        },
+                    );
+                }
+                self.diff_types(old_elements, new_elements, &format!("{path}[]"));
+            }
+            (
+                Definition::Sequence {
+                    elements: old_elements,
+                },
+                Definition::Sequence {
+                    elements: new_elements,
+                },
+            ) => self.diff_types(old_elements, new_elements, &format!("{path}[]")),
+            (
+                Definition::Tuple {
+                    elements: old_elements,
+                },
+                Definition::Tuple {
+                    elements: new_elements,
+                },
+            ) => {
+                let old_members = positional(old_elements);
+                let new_members = positional(new_elements);
+                self.diff_members(path, Member::Field, &old_members, &new_members);
+            }
+            (
+                Definition::Enum {
+                    variants: old_variants,
+                },
+                Definition::Enum {
+                    variants: new_variants,
+                },
+            ) => self.diff_members(path, Member::Variant, old_variants, new_variants),
+            (
+                Definition::Struct { fields: old_fields },
+                Definition::Struct { fields: new_fields },
+            ) => {
+                let old_members = struct_members(old_fields);
+                let new_members = struct_members(new_fields);
+                self.diff_members(path, Member::Field, &old_members, &new_members);
+            }
+            _ => self.push(path, type_changed),
+        }
+    }
+
+    fn diff_members(
+        &mut self,
+        path: &str,
+        member: Member,
+        old: &[(String, Declaration)],
+        new: &[(String, Declaration)],
+    ) {
+        let separator = match member {
+            Member::Field => ".",
+            Member::Variant => "::",
+        };
+        let position = |members: &[(String, Declaration)], name: &str| {
+            members.iter().position(|(other, _)| other == name)
+        };
+        // A member whose name disappeared while the one at the same position is new
+        // was renamed in place.
+        let renamed = |index: usize| {
+            index < old.len()
+                && index < new.len()
+                && position(new, &old[index].0).is_none()
+                && position(old, &new[index].0).is_none()
+        };
+
+        for (old_index, (name, old_type)) in old.iter().enumerate() {
+            let member_path = format!("{path}{separator}{name}");
+            if let Some(new_index) = position(new, name) {
+                if new_index != old_index {
+                    let kind = match member {
+                        Member::Field => ChangeKind::FieldReordered {
+                            old_index,
+                            new_index,
+                        },
+                        Member::Variant => ChangeKind::VariantReordered {
+                            old_index,
+                            new_index,
+                        },
+                    };
+                    self.push(&member_path, kind);
+                }
+                self.diff_types(old_type, &new[new_index].1, &member_path);
+            } else if renamed(old_index) {
+                let (new_name, new_type) = &new[old_index];
+                let new_path = format!("{path}{separator}{new_name}");
+                let old_name = name.clone();
+                let kind = match member {
+                    Member::Field => ChangeKind::FieldRenamed { old_name },
+                    Member::Variant => ChangeKind::VariantRenamed { old_name },
+                };
+                self.push(&new_path, kind);
+                self.diff_types(old_type, new_type, &new_path);
+            } else {
+                let kind = match member {
+                    Member::Field => ChangeKind::FieldRemoved,
+                    Member::Variant => ChangeKind::VariantRemoved,
+                };
+                self.push(&member_path, kind);
+            }
+        }
+
+        for (new_index, (name, _)) in new.iter().enumerate() {
+            if position(old, name).is_some() || renamed(new_index) {
+                continue;
+            }
+            let appended = new_index >= old.len();
+            let kind = match (member, appended) {
+                (Member::Field, true) => ChangeKind::FieldAppended,
+                (Member::Field, false) => ChangeKind::FieldInserted,
+                (Member::Variant, true) => ChangeKind::VariantAppended,
+                (Member::Variant, false) => ChangeKind::VariantInserted,
+            };
+            self.push(&format!("{path}{separator}{name}"), kind);
+        }
+    }
+}
+
+fn positional(declarations: &[Declaration]) -> Vec<(String, Declaration)> {
+    declarations
+        .iter()
+        .enumerate()
+        .map(|(index, declaration)| (index.to_string(), declaration.clone()))
+        .collect()
+}
+
+fn struct_members(fields: &Fields) -> Vec<(String, Declaration)> {
+    match fields {
+        Fields::NamedFields(fields) => fields.clone(),
+        Fields::UnnamedFields(fields) => positional(fields),
+        Fields::Empty => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Genre, MovieVersion};
+    use borsh::BorshSchema;
+
+    // These types only exist for their schemas.
+    #[allow(dead_code)]
+    mod old {
+        use borsh::BorshSchema;
+
+        #[derive(BorshSchema)]
+        pub enum Genre {
+            Comedy,
+            Drama,
+        }
+
+        #[derive(BorshSchema)]
+        pub struct Movie {
+            pub title: String,
+            pub genre: Genre,
+        }
+    }
+
+    #[allow(dead_code)]
+    mod appended {
+        use borsh::BorshSchema;
+
+        #[derive(BorshSchema)]
+        pub enum Genre {
+            Comedy,
+            Drama,
+            Fantasy,
+        }
+
+        #[derive(BorshSchema)]
+        pub struct Movie {
+            pub name: String,
+            pub genre: Genre,
+            pub imdb_url: String,
+        }
+    }
+
+    #[allow(dead_code)]
+    mod reordered {
+        use borsh::BorshSchema;
+
+        #[derive(BorshSchema)]
+        pub enum Genre {
+            Drama,
+            Comedy,
+        }
+
+        #[derive(BorshSchema)]
+        pub struct Movie {
+            pub genre: Genre,
+            pub title: Vec<u8>,
+        }
+    }
+
+    fn change(path: &str, kind: ChangeKind) -> SchemaChange {
+        SchemaChange {
+            path: path.into(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_identical_schemas() {
+        for version in MovieVersion::ALL {
+            assert_eq!(diff_schemas(&version.schema(), &version.schema()), vec![]);
+        }
+        let genre = Genre::schema_container();
+        assert_eq!(diff_schemas(&genre, &genre), vec![]);
+    }
+
+    #[test]
+    fn test_appended_members() {
+        let changes = diff_schemas(
+            &old::Movie::schema_container(),
+            &appended::Movie::schema_container(),
+        );
+        assert_eq!(
+            changes,
+            vec![
+                change(
+                    "Movie.name",
+                    ChangeKind::FieldRenamed {
+                        old_name: "title".into()
+                    }
+                ),
+                change("Movie.genre::Fantasy", ChangeKind::VariantAppended),
+                change("Movie.imdb_url", ChangeKind::FieldAppended),
+            ]
+        );
+        let labels: Vec<_> = changes.iter().map(SchemaChange::compatibility).collect();
+        assert_eq!(
+            labels,
+            [
+                Compatibility::Compatible,
+                Compatibility::Compatible,
+                Compatibility::NeedsMigration
+            ]
+        );
+        assert_eq!(
+            overall_compatibility(&changes),
+            Compatibility::NeedsMigration
+        );
+    }
+
+    #[test]
+    fn test_reordered_and_changed_members() {
+        let changes = diff_schemas(
+            &old::Movie::schema_container(),
+            &reordered::Movie::schema_container(),
+        );
+        assert_eq!(
+            changes,
+            vec![
+                change(
+                    "Movie.title",
+                    ChangeKind::FieldReordered {
+                        old_index: 0,
+                        new_index: 1
+                    }
+                ),
+                change(
+                    "Movie.title",
+                    ChangeKind::TypeRelaxed {
+                        old: "string".into(),
+                        new: "Vec<u8>".into()
+                    }
+                ),
+                change(
+                    "Movie.genre",
+                    ChangeKind::FieldReordered {
+                        old_index: 1,
+                        new_index: 0
+                    }
+                ),
+                change(
+                    "Movie.genre::Comedy",
+                    ChangeKind::VariantReordered {
+                        old_index: 0,
+                        new_index: 1
+                    }
+                ),
+                change(
+                    "Movie.genre::Drama",
+                    ChangeKind::VariantReordered {
+                        old_index: 1,
+                        new_index: 0
+                    }
+                ),
+            ]
+        );
+        assert_eq!(overall_compatibility(&changes), Compatibility::Breaking);
+        assert_eq!(changes[1].compatibility(), Compatibility::Compatible);
+
+        // The other way round, old byte vectors need not be valid strings.
+        let changes = diff_schemas(
+            &reordered::Movie::schema_container(),
+            &old::Movie::schema_container(),
+        );
+        let title = changes
+            .iter()
+            .find(|change| matches!(change.kind, ChangeKind::TypeChanged { .. }))
+            .unwrap();
+        assert_eq!(title.path, "Movie.title");
+        assert_eq!(title.compatibility(), Compatibility::Breaking);
+    }
+
+    #[test]
+    fn test_removed_members() {
+        let changes = diff_schemas(
+            &appended::Movie::schema_container(),
+            &old::Movie::schema_container(),
+        );
+        assert!(changes.contains(&change("Movie.genre::Fantasy", ChangeKind::VariantRemoved)));
+        assert!(changes.contains(&change("Movie.imdb_url", ChangeKind::FieldRemoved)));
+        assert_eq!(overall_compatibility(&changes), Compatibility::Breaking);
+    }
+}
@@ -0,0 +1,166 @@
+//! Serde support for text formats such as JSON.
+//!
+//! A `Movie` is an object with the fields `title`, `genre` and, for every version
+//! that has it, `imdb_url`. Records written before `imdb_url` existed leave it out
+//! rather than showing it empty, and an object without it is read as such a record.
+//! Genres are written by [`Genre::name`], or as their wire byte if this build does not
+//! know them. Like the Borsh encoding, these names must never change.
+
+use crate::{Genre, Movie, MovieV1};
+use serde::de::{self, Deserializer, Unexpected, Visitor};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+impl Serialize for Genre {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.name() {
+            Some(name) => serializer.serialize_str(name),
+            None => serializer.serialize_u8(self.as_u8()),
+        }
+    }
+}
+
+struct GenreVisitor;
+
+impl<'de> Visitor<'de> for GenreVisitor {
+    type Value = Genre;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a genre name or byte")
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<Genre, E> {
+        Genre::from_name(name).ok_or_else(|| E::custom(format!("unknown genre {name:?}")))
+    }
+
+    fn visit_u64<E: de::Error>(self, byte: u64) -> Result<Genre, E> {
+        u8::try_from(byte)
+            .map(Genre::from_u8)
+            .map_err(|_| E::invalid_value(Unexpected::Unsigned(byte), &self))
+    }
+
+    fn visit_i64<E: de::Error>(self, byte: i64) -> Result<Genre, E> {
+        u8::try_from(byte)
+            .map(Genre::from_u8)
+            .map_err(|_| E::invalid_value(Unexpected::Signed(byte), &self))
+    }
+}
+
+impl<'de> Deserialize<'de> for Genre {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(GenreVisitor)
+    }
+}
+
+#[derive(Serialize)]
+struct MovieRef<'a> {
+    title: &'a str,
+    genre: &'a Genre,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    imdb_url: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MovieFields {
+    title: String,
+    genre: Genre,
+    /// May be missing, but not `null`, so that a legacy record has a single form.
+    #[serde(default, deserialize_with = "present")]
+    imdb_url: Option<String>,
+}
+
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    String::deserialize(deserializer).map(Some)
+}
+
+impl Serialize for Movie {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        MovieRef {
+            title: self.title(),
+            genre: self.genre(),
+            imdb_url: self.imdb_url(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Movie {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let MovieFields {
+            title,
+            genre,
+            imdb_url,
+        } = MovieFields::deserialize(deserializer)?;
+        Ok(match imdb_url {
+            Some(imdb_url) => Movie::new(title, genre, imdb_url),
+            None => Movie::V1(MovieV1 { title, genre }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_genre_json() {
+        let names: Vec<_> = Genre::ALL
+            .iter()
+            .map(|genre| serde_json::to_value(genre).unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "comedy",
+                "drama",
+                "fantasy",
+                "horror",
+                "romance",
+                "science_fiction"
+            ]
+        );
+        assert_eq!(serde_json::to_value(Genre::from_u8(42)).unwrap(), json!(42));
+        for genre in Genre::ALL.into_iter().chain([Genre::from_u8(42)]) {
+            let value = serde_json::to_value(&genre).unwrap();
+            assert_eq!(serde_json::from_value::<Genre>(value).unwrap(), genre);
+        }
+        assert!(serde_json::from_value::<Genre>(json!("western")).is_err());
+        assert!(serde_json::from_value::<Genre>(json!(256)).is_err());
+    }
+
+    #[test]
+    fn test_movie_json() {
+        let movie = Movie::new(
+            "Back To The Future".into(),
+            Genre::ScienceFiction,
+            "https://www.imdb.com/title/tt0088763/".into(),
+        );
+        assert_eq!(
+            serde_json::to_string(&movie).unwrap(),
+            r#"{"title":"Back To The Future","genre":"science_fiction","imdb_url":"https://www.imdb.com/title/tt0088763/"}"#
+        );
+        let legacy = Movie::V1(MovieV1 {
+            title: "Back To The Future".into(),
+            genre: Genre::ScienceFiction,
+        });
+        assert_eq!(
+            serde_json::to_string(&legacy).unwrap(),
+            r#"{"title":"Back To The Future","genre":"science_fiction"}"#
+        );
+        for movie in [movie, legacy] {
+            let json = serde_json::to_string(&movie).unwrap();
+            assert_eq!(serde_json::from_str::<Movie>(&json).unwrap(), movie);
+        }
+    }
+
+    #[test]
+    fn test_movie_json_rejects_unknown_fields() {
+        let value = json!({"title": "Up", "genre": "comedy", "rating": 5});
+        assert!(serde_json::from_value::<Movie>(value).is_err());
+        let value = json!({"title": "Up", "genre": "comedy", "imdb_url": null});
+        assert!(serde_json::from_value::<Movie>(value).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+//! Canonical decoding: accepts exactly the byte strings that `Movie`'s
+//! `BorshSerialize` produces, so that every logical `Movie` has a single encoding.
+//!
+//! [`Movie::try_from_slice`] is lenient where that helps old nodes keep up: it keeps
+//! unknown genres and drops the unknown extension fields of newer versions. Neither
+//! is acceptable when the bytes are hashed, so the strict decoder rejects them.
+
+use crate::decode::{decode_movie, Mode, Reader};
+use crate::{DecodeError, Movie};
+
+impl Movie {
+    /// Decodes `bytes` only if they are the canonical encoding of a `Movie`, i.e.
+    /// exactly what [`BorshSerialize`](borsh::BorshSerialize) writes for it.
+    pub fn try_from_slice_strict(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes);
+        let movie = decode_movie(&mut reader, Mode::Strict)?;
+        reader.finish()?;
+        Ok(movie)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::golden::back_to_the_future;
+    use crate::{DecodeErrorKind, Field, MovieVersion, ENVELOPE_MARKER};
+
+    #[test]
+    fn test_strict_accepts_canonical_encodings() {
+        let legacy = back_to_the_future(MovieVersion::V1).bytes;
+        assert_eq!(
+            Movie::try_from_slice_strict(&legacy).unwrap(),
+            Movie::try_from_slice(&legacy).unwrap()
+        );
+        let bytes = back_to_the_future(MovieVersion::V2).bytes;
+        assert_eq!(
+            Movie::try_from_slice_strict(&bytes).unwrap(),
+            back_to_the_future(MovieVersion::V2).movie
+        );
+    }
+
+    #[test]
+    fn test_strict_rejects_trailing_bytes() {
+        let mut input = back_to_the_future(MovieVersion::V2).bytes;
+        input.extend_from_slice(&[0, 0]);
+        assert!(matches!(
+            Movie::try_from_slice_strict(&input),
+            Err(DecodeError {
+                kind: DecodeErrorKind::TrailingBytes(2),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_strict_rejects_invalid_utf8() {
+        let mut input = back_to_the_future(MovieVersion::V1).bytes;
+        input[4] = 0xc3;
+        assert!(matches!(
+            Movie::try_from_slice_strict(&input),
+            Err(DecodeError {
+                field: Field::Title,
+                kind: DecodeErrorKind::InvalidUtf8,
+                ..
+            })
+        ));
+        let mut input = back_to_the_future(MovieVersion::V2).bytes;
+        let last = input.len() - 1;
+        input[last] = 0xff;
+        assert!(matches!(
+            Movie::try_from_slice_strict(&input),
+            Err(DecodeError {
+                field: Field::ImdbUrl,
+                kind: DecodeErrorKind::InvalidUtf8,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_strict_rejects_unknown_genre() {
+        let input = hex::decode("120000004261636b20546f20546865204675747572652a").unwrap();
+        assert!(Movie::try_from_slice(&input).is_ok());
+        assert!(matches!(
+            Movie::try_from_slice_strict(&input),
+            Err(DecodeError {
+                kind: DecodeErrorKind::UnknownGenre(0x2a),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_strict_rejects_newer_versions() {
+        let mut input = back_to_the_future(MovieVersion::V2).bytes;
+        input[ENVELOPE_MARKER.len()] = MovieVersion::LATEST.as_u8() + 1;
+        assert!(Movie::try_from_slice(&input).is_ok());
+        assert!(matches!(
+            Movie::try_from_slice_strict(&input),
+            Err(DecodeError {
+                kind: DecodeErrorKind::NewerVersion(3),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_strict_rejects_truncated_input() {
+        let input = back_to_the_future(MovieVersion::V2).bytes;
+        for end in 0..input.len() {
+            assert!(matches!(
+                Movie::try_from_slice_strict(&input[..end]),
+                Err(DecodeError {
+                    kind: DecodeErrorKind::UnexpectedEnd { .. },
+                    ..
+                })
+            ));
+        }
+    }
+}
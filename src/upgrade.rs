@@ -0,0 +1,123 @@
+use crate::{Movie, MovieV1, MovieV2};
+use std::fmt::Write;
+
+const IMDB_SEARCH_URL: &str = "https://www.imdb.com/find/?s=tt&q=";
+
+/// The `imdb_url` given to V1 records that are upgraded without an explicit URL.
+///
+/// This is an IMDB title search for the movie's title, so it is always a valid link
+/// and depends only on data that the V1 record already contains.
+pub fn imdb_search_url(title: &str) -> String {
+    let mut url = String::from(IMDB_SEARCH_URL);
+    for byte in title.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                url.push(byte as char)
+            }
+            _ => write!(url, "%{byte:02X}").expect("writing to a String cannot fail"),
+        }
+    }
+    url
+}
+
+impl MovieV1 {
+    pub fn upgrade_with(self, imdb_url: String) -> MovieV2 {
+        MovieV2 {
+            title: self.title,
+            genre: self.genre,
+            imdb_url,
+        }
+    }
+}
+
+impl From<MovieV1> for MovieV2 {
+    fn from(movie: MovieV1) -> Self {
+        let imdb_url = imdb_search_url(&movie.title);
+        movie.upgrade_with(imdb_url)
+    }
+}
+
+impl Movie {
+    /// Converts any historical record into the latest layout, filling fields that
+    /// did not exist yet with their default rule (see [`imdb_search_url`]).
+    pub fn into_latest(self) -> MovieV2 {
+        self.into_latest_with(|movie| imdb_search_url(&movie.title))
+    }
+
+    /// Like [`Movie::into_latest`], but `imdb_url` is supplied for V1 records by the caller,
+    /// e.g. from an off-chain lookup table.
+    pub fn into_latest_with<F>(self, imdb_url: F) -> MovieV2
+    where
+        F: FnOnce(&MovieV1) -> String,
+    {
+        match self {
+            Self::V1(movie) => {
+                let imdb_url = imdb_url(&movie);
+                movie.upgrade_with(imdb_url)
+            }
+            Self::V2(movie) => movie,
+        }
+    }
+
+    /// Whether the record is already in the latest layout.
+    pub fn is_latest(&self) -> bool {
+        matches!(self, Self::V2(_))
+    }
+}
+
+impl From<Movie> for MovieV2 {
+    fn from(movie: Movie) -> Self {
+        movie.into_latest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::golden::back_to_the_future;
+    use crate::{Genre, MovieVersion};
+
+    #[test]
+    fn test_upgrade_v1_uses_search_url() {
+        let upgraded = back_to_the_future(MovieVersion::V1).movie.into_latest();
+        assert_eq!(
+            upgraded,
+            MovieV2 {
+                title: "Back To The Future".into(),
+                genre: Genre::ScienceFiction,
+                imdb_url: "https://www.imdb.com/find/?s=tt&q=Back%20To%20The%20Future".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_upgrade_with_explicit_url() {
+        let imdb_url = "https://www.imdb.com/title/tt0088763/";
+        let upgraded = back_to_the_future(MovieVersion::V1)
+            .movie
+            .into_latest_with(|_| imdb_url.into());
+        assert_eq!(upgraded.imdb_url, imdb_url);
+    }
+
+    #[test]
+    fn test_upgrade_latest_is_unchanged() {
+        let movie = Movie::new(
+            "Amélie".into(),
+            Genre::Romance,
+            "https://www.imdb.com/title/tt0211915/".into(),
+        );
+        assert!(movie.is_latest());
+        let upgraded = movie
+            .clone()
+            .into_latest_with(|_| unreachable!("V2 records already have a URL"));
+        assert_eq!(Movie::V2(upgraded), movie);
+    }
+
+    #[test]
+    fn test_search_url_escapes_non_ascii() {
+        assert_eq!(
+            imdb_search_url("Amélie & co"),
+            "https://www.imdb.com/find/?s=tt&q=Am%C3%A9lie%20%26%20co"
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Checks that every record of a dump still decodes and re-encodes to the exact bytes
+//! it was read from.
+
+use crate::dump::{Location, Record, Records};
+use crate::{DecodeError, Movie, MovieVersion};
+use borsh::BorshSerialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufRead};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The record's text is not valid in the dump's encoding.
+    Unreadable(String),
+    Undecodable(DecodeError),
+    /// The record decodes, but re-encodes to these different bytes.
+    NotRoundTrip(Vec<u8>),
+    /// The dump ends in the middle of this record, so nothing after it was read.
+    Truncated,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub location: Location,
+    pub problem: Problem,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.problem {
+            Problem::Unreadable(err) => write!(f, "{}: {}", self.location, err),
+            Problem::Undecodable(err) => write!(f, "{}: {}", self.location, err),
+            Problem::NotRoundTrip(bytes) => {
+                write!(f, "{}: re-encodes as {}", self.location, hex::encode(bytes))
+            }
+            Problem::Truncated => write!(f, "{}: truncated record", self.location),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub records: usize,
+    /// Records that round trip, by the version they were written in.
+    pub versions: BTreeMap<MovieVersion, usize>,
+    pub failures: Vec<Failure>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    fn check(&mut self, record: Record) {
+        self.records += 1;
+        let problem = match record.bytes {
+            Err(err) => Problem::Unreadable(err),
+            Ok(bytes) => match Movie::try_from_slice(&bytes) {
+                Err(err) => Problem::Undecodable(err),
+                Ok(movie) => {
+                    let reencoded = movie.try_to_vec().expect("writing to a Vec cannot fail");
+                    if reencoded == bytes {
+                        *self.versions.entry(movie.version()).or_default() += 1;
+                        return;
+                    }
+                    Problem::NotRoundTrip(reencoded)
+                }
+            },
+        };
+        self.failures.push(Failure {
+            location: record.location,
+            problem,
+        });
+    }
+}
+
+/// Verifies every record. A truncated last record is reported as a failure; only a
+/// dump that cannot be read at all is an error.
+pub fn verify<R: BufRead>(mut records: Records<R>) -> io::Result<Report> {
+    let mut report = Report::default();
+    while let Some(record) = records.next() {
+        match record {
+            Ok(record) => report.check(record),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                report.records += 1;
+                report.failures.push(Failure {
+                    location: records.location(),
+                    problem: Problem::Truncated,
+                });
+                break;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(report)
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "records: {}", self.records)?;
+        for version in MovieVersion::ALL {
+            let count = self.versions.get(&version).copied().unwrap_or_default();
+            writeln!(f, "{version:?}: {count}")?;
+        }
+        writeln!(f, "failures: {}", self.failures.len())?;
+        for failure in &self.failures {
+            writeln!(f, "  {failure}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Encoding;
+    use crate::dump::{write_record, Format};
+    use crate::golden::back_to_the_future;
+    use crate::{Genre, ENVELOPE_MARKER};
+
+    #[test]
+    fn test_verify_report() {
+        let legacy = hex::encode(back_to_the_future(MovieVersion::V1).bytes);
+        let tagged = Movie::new("Up".into(), Genre::Comedy, "u".into())
+            .try_to_vec()
+            .unwrap();
+        let mut newer = tagged.clone();
+        newer[ENVELOPE_MARKER.len()] = MovieVersion::LATEST.as_u8() + 1;
+        let dump = [
+            legacy.clone(),
+            hex::encode(&tagged),
+            legacy.clone(),
+            "zz".into(),
+            hex::encode(&newer),
+            legacy[..10].to_string(),
+        ]
+        .join("\n");
+        let report = verify(Records::new(dump.as_bytes(), Format::Lines(Encoding::Hex))).unwrap();
+        assert_eq!(report.records, 6);
+        assert!(!report.is_ok());
+        assert_eq!(
+            report.to_string(),
+            format!(
+                r#"records: 6
+V1: 2
+V2: 1
+failures: 3
+  line 4: invalid hex: Invalid character 'z' at position 0
+  line 5: re-encodes as {}
+  line 6: Movie title at byte 4: needs 18 bytes but only 1 are left
+"#,
+                hex::encode(&tagged)
+            )
+        );
+    }
+
+    #[test]
+    fn test_verify_reports_truncation() {
+        let mut dump = Vec::new();
+        write_record(
+            &mut dump,
+            Format::Framed,
+            &back_to_the_future(MovieVersion::V1).bytes,
+        )
+        .unwrap();
+        let end = dump.len();
+        write_record(&mut dump, Format::Framed, b"abc").unwrap();
+        dump.truncate(dump.len() - 1);
+        let report = verify(Records::new(dump.as_slice(), Format::Framed)).unwrap();
+        assert_eq!(report.records, 2);
+        assert_eq!(report.versions[&MovieVersion::V1], 1);
+        assert_eq!(
+            report.failures,
+            [Failure {
+                location: Location::Offset(end as u64),
+                problem: Problem::Truncated,
+            }]
+        );
+        assert_eq!(
+            report.failures[0].to_string(),
+            "offset 27: truncated record"
+        );
+    }
+}